@@ -0,0 +1,57 @@
+use arbolito::ByteTrie16;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::{Rng, SeedableRng};
+use rand_isaac::IsaacRng;
+
+const NUM_QUERIES: usize = 1 << 20;
+
+fn setup() -> (ByteTrie16, Vec<([u8; 8], usize)>) {
+    let keys: Vec<Vec<u8>> = vec![
+        b"get".to_vec(),
+        b"gets".to_vec(),
+        b"put".to_vec(),
+        b"post".to_vec(),
+        b"patch".to_vec(),
+    ];
+    let trie = ByteTrie16::from_keys(keys.clone()).unwrap();
+
+    let mut rng = IsaacRng::seed_from_u64(0);
+    let queries = (0..NUM_QUERIES)
+        .map(|_| {
+            let mut query = [0u8; 8];
+            let key = &keys[rng.gen_range(0, keys.len())];
+            query[..key.len()].copy_from_slice(key);
+            if rng.gen_bool(0.5) {
+                query[rng.gen_range(0, key.len())] ^= 1;
+            }
+            (query, key.len())
+        })
+        .collect();
+    (trie, queries)
+}
+
+// How `par_traverse` scales with the size of the thread pool.
+fn bench_par_traverse(c: &mut Criterion) {
+    let (trie, queries) = setup();
+    let mut group = c.benchmark_group("par_traverse");
+    group.throughput(Throughput::Elements(NUM_QUERIES as u64));
+
+    let max_threads = num_cpus();
+    let mut num_threads = 1;
+    while num_threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(num_threads), &num_threads, |b, _| {
+            b.iter(|| pool.install(|| black_box(trie.par_traverse(black_box(&queries)))))
+        });
+        num_threads *= 2;
+    }
+
+    group.finish();
+}
+
+fn num_cpus() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+criterion_group!(benches, bench_par_traverse);
+criterion_main!(benches);
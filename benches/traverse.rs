@@ -0,0 +1,285 @@
+use arbolito::{ByteTrie16, Lookup};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::collections::BTreeMap;
+use rand::{Rng, SeedableRng};
+use rand_isaac::IsaacRng;
+
+const NUM_QUERIES: usize = 1024;
+
+// A trie with a few shared prefixes, and a mix of queries that hit it and queries that don't.
+fn setup() -> (ByteTrie16, Vec<([u8; 8], usize)>) {
+    let keys: Vec<Vec<u8>> = vec![
+        b"get".to_vec(),
+        b"gets".to_vec(),
+        b"put".to_vec(),
+        b"post".to_vec(),
+        b"patch".to_vec(),
+    ];
+    let trie = ByteTrie16::from_keys(keys.clone()).unwrap();
+
+    let mut rng = IsaacRng::seed_from_u64(0);
+    let queries = (0..NUM_QUERIES)
+        .map(|_| {
+            let mut query = [0u8; 8];
+            let key = &keys[rng.gen_range(0, keys.len())];
+            query[..key.len()].copy_from_slice(key);
+            if rng.gen_bool(0.5) {
+                query[rng.gen_range(0, key.len())] ^= 1;
+            }
+            (query, key.len())
+        })
+        .collect();
+    (trie, queries)
+}
+
+fn bench_traverse(c: &mut Criterion) {
+    let (trie, queries) = setup();
+    let mut group = c.benchmark_group("traverse");
+    group.throughput(Throughput::Elements(NUM_QUERIES as u64));
+
+    group.bench_function("single", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for ((query, query_len), slot) in queries.iter().zip(out.iter_mut()) {
+                *slot = trie.traverse(black_box(query), *query_len);
+            }
+            black_box(&out);
+        })
+    });
+
+    group.bench_function("batch", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            trie.traverse_batch(black_box(&queries), &mut out);
+            black_box(&out);
+        })
+    });
+
+    group.bench_function("sequential4", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for (chunk, slots) in queries.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+                for ((query, query_len), slot) in chunk.iter().zip(slots) {
+                    *slot = trie.traverse(black_box(query), *query_len);
+                }
+            }
+            black_box(&out);
+        })
+    });
+
+    group.bench_function("traverse4", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for (chunk, slots) in queries.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+                let batch = [chunk[0].0, chunk[1].0, chunk[2].0, chunk[3].0];
+                let lens = [chunk[0].1, chunk[1].1, chunk[2].1, chunk[3].1];
+                let lookups = trie.traverse4(black_box(&batch), &lens);
+                for (lookup, slot) in IntoIterator::into_iter(lookups).zip(slots) {
+                    *slot = lookup;
+                }
+            }
+            black_box(&out);
+        })
+    });
+
+    // The same queries with a first byte no key starts with, so every one of them misses right
+    // away.
+    let misses: Vec<_> = queries
+        .iter()
+        .map(|&(mut query, query_len)| {
+            query[0] = b'z';
+            (query, query_len)
+        })
+        .collect();
+    group.bench_function("misses", |b| {
+        let mut out: Vec<Lookup> = misses.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for ((query, query_len), slot) in misses.iter().zip(out.iter_mut()) {
+                *slot = trie.traverse(black_box(query), *query_len);
+            }
+            black_box(&out);
+        })
+    });
+
+    // The same keys with bytes left over past the end, so every query is deeper than the trie.
+    let too_long: Vec<_> = queries
+        .iter()
+        .map(|&(mut query, query_len)| {
+            for byte in &mut query[query_len..] {
+                *byte = b'x';
+            }
+            (query, 8)
+        })
+        .collect();
+    group.bench_function("too_long", |b| {
+        let mut out: Vec<Lookup> = too_long.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for ((query, query_len), slot) in too_long.iter().zip(out.iter_mut()) {
+                *slot = trie.traverse(black_box(query), *query_len);
+            }
+            black_box(&out);
+        })
+    });
+
+    group.finish();
+}
+
+// Whether a `traverse_with_scratch` would pay for itself. `traverse` has no temporaries left to
+// keep between calls, only the lanes' setup, and `traverse_batch` already does that once for all
+// the queries, so `reused_setup` is the most a scratch buffer could save. `zeroed_temporaries`
+// goes the other way: it adds back zeroing the `[u8x16; 8]` and `[u8x16; 4]` arrays a scratch
+// buffer would have held. `black_box` makes them hit memory, so that's their worst case.
+fn bench_scratch(c: &mut Criterion) {
+    let (trie, queries) = setup();
+    let mut group = c.benchmark_group("scratch");
+    group.throughput(Throughput::Elements(NUM_QUERIES as u64));
+
+    group.bench_function("single", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for ((query, query_len), slot) in queries.iter().zip(out.iter_mut()) {
+                *slot = trie.traverse(black_box(query), *query_len);
+            }
+            black_box(&out);
+        })
+    });
+
+    group.bench_function("reused_setup", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for (chunk, slots) in queries.chunks(64).zip(out.chunks_mut(64)) {
+                trie.traverse_batch(black_box(chunk), slots);
+            }
+            black_box(&out);
+        })
+    });
+
+    group.bench_function("zeroed_temporaries", |b| {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for ((query, query_len), slot) in queries.iter().zip(out.iter_mut()) {
+                black_box([[0u8; 16]; 8]);
+                black_box([[0u8; 16]; 4]);
+                *slot = trie.traverse(black_box(query), *query_len);
+            }
+            black_box(&out);
+        })
+    });
+
+    group.finish();
+}
+
+// Exactly 16 nodes, with keys of length 1, 4, and 8.
+fn full_keys() -> Vec<Vec<u8>> {
+    vec![b"a".to_vec(), b"host".to_vec(), b"link".to_vec(), b"location".to_vec()]
+}
+
+// The obvious pointer-chasing trie to compare against: one map lookup per query byte.
+struct Baseline {
+    children: BTreeMap<(Option<usize>, u8), usize>,
+    values: Vec<Option<u8>>,
+}
+
+impl Baseline {
+    fn new(keys: &[Vec<u8>]) -> Self {
+        let mut children = BTreeMap::new();
+        let mut values = vec![];
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        for (rank, key) in sorted.iter().enumerate() {
+            let mut node = None;
+            for &label in key {
+                let next = values.len();
+                let child = *children.entry((node, label)).or_insert(next);
+                if child == next {
+                    values.push(None);
+                }
+                node = Some(child);
+            }
+            values[node.unwrap()] = Some(rank as u8);
+        }
+        Self { children, values }
+    }
+
+    fn traverse(&self, query: &[u8]) -> Lookup {
+        let mut node = None;
+        for &label in query {
+            match self.children.get(&(node, label)) {
+                Some(&child) => node = Some(child),
+                None => return Lookup::NoSuchPath,
+            }
+        }
+        match node.and_then(|n| self.values[n]) {
+            Some(rank) => Lookup::Value(rank),
+            None => Lookup::NoSuchPath,
+        }
+    }
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let keys = full_keys();
+    let trie = ByteTrie16::from_keys(keys.clone()).unwrap();
+    let baseline = Baseline::new(&keys);
+    let mut group = c.benchmark_group("lookup");
+
+    for &(name, key) in &[("len1", &b"a"[..]), ("len4", b"host"), ("len8", b"location"), ("miss", b"locatiom"), ("miss_first", b"xocation")] {
+        let mut query = [0u8; 8];
+        query[..key.len()].copy_from_slice(key);
+        assert_eq!(trie.traverse(&query, key.len()), baseline.traverse(key));
+
+        group.bench_with_input(BenchmarkId::new("trie", name), &query, |b, query| {
+            b.iter(|| trie.traverse(black_box(query), key.len()))
+        });
+        group.bench_with_input(BenchmarkId::new("btree", name), key, |b, key| {
+            b.iter(|| baseline.traverse(black_box(key)))
+        });
+    }
+
+    group.finish();
+}
+
+// One query against eight small tries, most of which don't have it.
+fn bench_gather(c: &mut Criterion) {
+    let mut rng = IsaacRng::seed_from_u64(0);
+    let tries: [ByteTrie16; 8] = core::array::from_fn(|_| {
+        let keys = (0..4).map(|_| (0..rng.gen_range(1, 4)).map(|_| rng.gen_range(b'a', b'e')).collect::<Vec<u8>>());
+        ByteTrie16::from_keys(keys.collect::<Vec<_>>()).unwrap()
+    });
+    let queries: Vec<([u8; 8], usize)> = (0..NUM_QUERIES)
+        .map(|_| {
+            let len = rng.gen_range(1, 4);
+            let mut query = [0u8; 8];
+            for byte in &mut query[..len] {
+                *byte = rng.gen_range(b'a', b'e');
+            }
+            (query, len)
+        })
+        .collect();
+    let mut group = c.benchmark_group("gather");
+    group.throughput(Throughput::Elements(NUM_QUERIES as u64));
+
+    group.bench_function("loop", |b| {
+        let mut out: Vec<Lookup> = tries.iter().map(|_| Lookup::NoSuchPath).collect();
+        b.iter(|| {
+            for (query, query_len) in &queries {
+                for (trie, slot) in tries.iter().zip(out.iter_mut()) {
+                    *slot = trie.traverse(black_box(query), *query_len);
+                }
+                black_box(&out);
+            }
+        })
+    });
+
+    group.bench_function("traverse_gather", |b| {
+        b.iter(|| {
+            for (query, query_len) in &queries {
+                black_box(ByteTrie16::traverse_gather(&tries, black_box(query), *query_len));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_traverse, bench_lookup, bench_gather, bench_scratch);
+criterion_main!(benches);
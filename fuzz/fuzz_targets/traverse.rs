@@ -0,0 +1,104 @@
+// Checks `traverse` against a slow edge-at-a-time walk on arbitrary tries of up to 16 edges and 8
+// levels. Run with `cargo +nightly fuzz run traverse` from the crate root.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use arbolito::{ByteTrie16, Edge, Lookup};
+use libfuzzer_sys::fuzz_target;
+use std::collections::BTreeSet;
+
+const MAX_EDGES: usize = 16;
+const MAX_DEPTH: usize = 8;
+
+#[derive(Arbitrary, Debug)]
+struct RawEdge {
+    // Picks the parent out of the edges before this one, or a root when it's zero.
+    parent: u8,
+    label: u8,
+    has_value: bool,
+    has_branch: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    edges: Vec<RawEdge>,
+    queries: Vec<Vec<u8>>,
+}
+
+// Turns raw edges into a set `ByteTrie16::new` accepts: numbers in order, parents that come
+// earlier, no path deeper than `MAX_DEPTH`, and no repeated label under a node. Edges that would
+// break any of those are dropped rather than fixed up, so the fuzzer still reaches full tries.
+fn edges(raw: &[RawEdge]) -> Vec<Edge> {
+    let mut edges: Vec<Edge> = vec![];
+    let mut depths = vec![];
+    let mut labels = BTreeSet::new();
+    for r in raw {
+        if edges.len() == MAX_EDGES {
+            break;
+        }
+        let parent = match (r.parent as usize, edges.len()) {
+            (0, _) | (_, 0) => None,
+            (p, n) => Some(p % n),
+        };
+        let depth = parent.map_or(1, |p| depths[p] + 1);
+        if depth > MAX_DEPTH || !labels.insert((parent, r.label)) {
+            continue;
+        }
+        edges.push(Edge {
+            parent,
+            label: r.label,
+            number: edges.len(),
+            has_value: r.has_value,
+            has_branch: r.has_branch,
+        });
+        depths.push(depth);
+    }
+    edges
+}
+
+// Follows `query` one edge at a time, like `TestTree::traverse` in the crate's tests.
+fn slow_traverse(edges: &[Edge], query: &[u8]) -> Lookup {
+    if query.is_empty() {
+        return Lookup::NoSuchPath;
+    }
+    let mut node = None;
+    for &byte in query {
+        match edges.iter().find(|e| e.parent == node && e.label == byte) {
+            Some(e) => node = Some(e.number),
+            None => return Lookup::NoSuchPath,
+        }
+    }
+    let e = &edges[node.unwrap()];
+    let value_rank = edges[..e.number].iter().filter(|e| e.has_value).count() as u8;
+    let branch_rank = edges[..e.number].iter().filter(|e| e.has_branch).count() as u8;
+    match (e.has_value, e.has_branch) {
+        (true, true) => Lookup::ValueAndBranch(value_rank, branch_rank),
+        (true, false) => Lookup::Value(value_rank),
+        (false, true) => Lookup::Branch(branch_rank),
+        (false, false) => Lookup::Exists,
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let edges = edges(&input.edges);
+    let trie = ByteTrie16::new(&edges.iter().cloned().collect());
+
+    // Every path in the trie, plus whatever the fuzzer came up with.
+    let mut queries = input.queries;
+    for e in &edges {
+        let mut path = vec![e.label];
+        let mut parent = e.parent;
+        while let Some(p) = parent {
+            path.push(edges[p].label);
+            parent = edges[p].parent;
+        }
+        path.reverse();
+        queries.push(path);
+    }
+
+    for query in queries.iter().filter(|q| q.len() <= 8) {
+        let mut padded = [0u8; 8];
+        padded[..query.len()].copy_from_slice(query);
+        assert_eq!(trie.traverse(&padded, query.len()), slow_traverse(&edges, query), "query {:?}", query);
+    }
+});
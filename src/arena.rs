@@ -0,0 +1,69 @@
+use crate::{ByteTrie, LaneCount, SupportedLaneCount};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Many tries with their values in one flat `Vec`, like a `ByteMap` per trie without a separate
+// allocation for each one's values. Trie `i`'s values are `values[offsets[i]..offsets[i + 1]]`,
+// in rank order.
+pub struct TrieArena<const LANES: usize, V>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    tries: Vec<ByteTrie<LANES>>,
+    values: Vec<V>,
+    offsets: Vec<usize>,
+}
+
+pub type TrieArena16<V> = TrieArena<16, V>;
+pub type TrieArena32<V> = TrieArena<32, V>;
+pub type TrieArena64<V> = TrieArena<64, V>;
+
+impl<const LANES: usize, V> TrieArena<LANES, V>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    pub fn new() -> Self {
+        Self { tries: vec![], values: vec![], offsets: vec![0] }
+    }
+
+    // Adds a trie and returns its index. `values` must yield one value per value in the trie, in
+    // rank order.
+    pub fn push(&mut self, trie: ByteTrie<LANES>, values: impl IntoIterator<Item = V>) -> usize {
+        let start = self.values.len();
+        self.values.extend(values);
+        assert_eq!(self.values.len() - start, trie.num_values() as usize);
+        self.tries.push(trie);
+        self.offsets.push(self.values.len());
+        self.tries.len() - 1
+    }
+
+    // The value `trie_ix`'s trie has at the end of the query, if any.
+    pub fn get(&self, trie_ix: usize, query: &[u8; 8], query_len: usize) -> Option<&V> {
+        self.tries[trie_ix].value_rank(query, query_len).map(|rank| &self.values(trie_ix)[rank as usize])
+    }
+
+    pub fn trie(&self, trie_ix: usize) -> &ByteTrie<LANES> {
+        &self.tries[trie_ix]
+    }
+
+    pub fn values(&self, trie_ix: usize) -> &[V] {
+        &self.values[self.offsets[trie_ix]..self.offsets[trie_ix + 1]]
+    }
+
+    pub fn len(&self) -> usize {
+        self.tries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tries.is_empty()
+    }
+}
+
+impl<const LANES: usize, V> Default for TrieArena<LANES, V>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{BuildError, ByteTrie16, Edge};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Handle to a node added to a `TrieBuilder`. It only means something to the builder that handed it
+// out: another builder's `NodeId` names whichever of its own nodes has the same number, if any.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct NodeId(usize);
+
+// Builds a `ByteTrie16` one node at a time, numbering edges in insertion order so callers never
+// have to keep `Edge::number` and `Edge::parent` in sync themselves.
+#[derive(Default)]
+pub struct TrieBuilder {
+    edges: Vec<Edge>,
+}
+
+impl TrieBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_root(&mut self, label: u8) -> Result<NodeId, BuildError> {
+        self.add(None, label)
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, label: u8) -> Result<NodeId, BuildError> {
+        self.add(Some(parent), label)
+    }
+
+    // Panics if `node` is past the last node added, which can only happen with a `NodeId` from
+    // another builder.
+    pub fn set_value(&mut self, node: NodeId) {
+        self.node_mut(node).has_value = true;
+    }
+
+    // Panics like `set_value`.
+    pub fn set_branch(&mut self, node: NodeId) {
+        self.node_mut(node).has_branch = true;
+    }
+
+    // `add_root` and `add_child` turn away anything `ByteTrie16::new` would, so this can't panic.
+    pub fn build(self) -> ByteTrie16 {
+        ByteTrie16::new(&self.edges.into_iter().collect())
+    }
+
+    // Same as `build`, plus the path to each value as a string, in value rank order so that
+    // `Lookup::Value(rank)` indexes straight into it. Bytes that aren't UTF-8 come out as U+FFFD.
+    pub fn build_with_names(self) -> (ByteTrie16, Vec<String>) {
+        let names = self
+            .edges
+            .iter()
+            .filter(|e| e.has_value)
+            .map(|e| {
+                let mut path = vec![e.label];
+                let mut parent = e.parent;
+                while let Some(p) = parent {
+                    path.push(self.edges[p].label);
+                    parent = self.edges[p].parent;
+                }
+                path.reverse();
+                String::from_utf8_lossy(&path).into_owned()
+            })
+            .collect();
+        (self.build(), names)
+    }
+
+    fn add(&mut self, parent: Option<NodeId>, label: u8) -> Result<NodeId, BuildError> {
+        let number = self.edges.len();
+        if number >= 16 {
+            return Err(BuildError::TooManyNodes(number + 1));
+        }
+        let parent = parent.map(|p| p.0);
+        if let Some(p) = parent.filter(|&p| p >= number) {
+            return Err(BuildError::OrphanEdge { number, parent: p });
+        }
+        if self.edges.iter().any(|e| (e.parent, e.label) == (parent, label)) {
+            return Err(BuildError::DuplicateSiblingLabel { parent, label });
+        }
+        self.edges.push(Edge {
+            parent,
+            label,
+            number,
+            has_value: false,
+            has_branch: false,
+        });
+        Ok(NodeId(number))
+    }
+
+    fn node_mut(&mut self, node: NodeId) -> &mut Edge {
+        let len = self.edges.len();
+        self.edges.get_mut(node.0).unwrap_or_else(|| panic!("node {} isn't one of this builder's {}", node.0, len))
+    }
+}
@@ -0,0 +1,66 @@
+use crate::simd::{Bitmask, Mask, Vector, VectorOf};
+use crate::{classify, ByteTrie, LaneCount, Lanes, Lookup, SupportedLaneCount};
+
+// Follows a query one byte at a time, for callers that can't buffer the whole thing. Each step is
+// one level of `match_levels`: shuffle the current matches down to the children and keep the ones
+// whose label is the new byte. Since it tracks lanes rather than query positions, paths of any
+// depth work.
+#[derive(Clone, Copy)]
+pub struct Cursor<const LANES: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    lanes: Lanes<VectorOf<LANES>>,
+    // All ones in the lane at the end of the path so far. Meaningless before the first step.
+    active: VectorOf<LANES>,
+    started: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CursorState {
+    // The bytes so far aren't a path in the trie, and no more bytes will make them one.
+    Dead,
+    // The bytes so far are a path, but nothing ends there.
+    Partial,
+    // The bytes so far end at a value or branch, which is what `traverse` would return for them.
+    // Never `Lookup::NoSuchPath` or `Lookup::Exists`.
+    Match(Lookup),
+}
+
+impl<const LANES: usize> Cursor<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    pub(crate) fn new(trie: &ByteTrie<LANES>) -> Self {
+        let lanes = Lanes::new(trie.nodes, trie.edges);
+        Self { active: lanes.roots, lanes, started: false }
+    }
+
+    pub fn step(&mut self, byte: u8) -> CursorState {
+        let zero = VectorOf::<LANES>::splat(0);
+        // Once nothing matches, nothing ever will again.
+        if self.started && self.active == zero {
+            return CursorState::Dead;
+        }
+        let ones = VectorOf::<LANES>::splat(0xFF);
+        let labels = self.lanes.edges.simd_eq(VectorOf::<LANES>::splat(byte)).select(ones, zero);
+
+        // Roots have no parent to inherit a match from, so only the first byte can land on them.
+        let candidates = if self.started {
+            self.active.shuffle(self.lanes.parents) & self.lanes.children
+        } else {
+            self.lanes.roots
+        };
+        self.active = candidates & labels;
+        self.started = true;
+
+        let match_mask = self.active.simd_ne(zero).bitmask();
+        if match_mask.count_ones() == 0 {
+            return CursorState::Dead;
+        }
+        match classify(self.lanes.nodes, match_mask) {
+            Lookup::Exists => CursorState::Partial,
+            lookup => CursorState::Match(lookup),
+        }
+    }
+}
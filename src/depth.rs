@@ -0,0 +1,50 @@
+use crate::{
+    build_tree, debug_assert_padded, traverse, BuildError, ByteTrie, Edge, LaneCount, Lanes, Lookup, SupportedLaneCount,
+};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+// A `ByteTrie` whose paths are at most `D` edges deep, for `D` from 1 to 8. Queries are `[u8; D]`,
+// so a shallow trie doesn't carry 8 bytes around per key, and `traverse` only ever builds `D`
+// bitsets and shuffles at most `D - 1` times.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ByteTrieDepth<const LANES: usize, const D: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    trie: ByteTrie<LANES>,
+}
+
+impl<const LANES: usize, const D: usize> ByteTrieDepth<LANES, D>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // Match bits are a byte per lane, so there's room for 8 query positions.
+    const DEPTH_IN_RANGE: () = assert!(D >= 1 && D <= 8, "depth has to be between 1 and 8");
+
+    // Panics if `try_new` would return an error.
+    pub fn new(edges: &BTreeSet<Edge>) -> Self {
+        Self::try_new(edges).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Same as `ByteTrie::try_new`, but paths deeper than `D` are `DepthExceeded`.
+    pub fn try_new(edges: &BTreeSet<Edge>) -> Result<Self, BuildError> {
+        let _: () = Self::DEPTH_IN_RANGE;
+        if edges.len() > LANES {
+            return Err(BuildError::TooManyNodes(edges.len()));
+        }
+        let sorted: Vec<Edge> = edges.iter().copied().collect();
+        let (packed_edges, packed_nodes) = build_tree::<LANES, _>(&sorted, D)?;
+        Ok(Self { trie: ByteTrie::from_packed(packed_edges, packed_nodes) })
+    }
+
+    // Same as `ByteTrie::traverse`, over `D` query bytes.
+    pub fn traverse(&self, query: &[u8; D], query_len: usize) -> Lookup {
+        debug_assert_padded(query, query_len);
+        traverse(&Lanes::new(self.trie.nodes, self.trie.edges), query, query_len)
+    }
+
+    pub fn trie(&self) -> &ByteTrie<LANES> {
+        &self.trie
+    }
+}
@@ -0,0 +1,62 @@
+use crate::simd::{NodeBytes, Vector, VectorOf};
+use crate::{
+    build_tree, classify, debug_assert_padded, match_path, BuildError, Edge, Lanes, Lookup, MAX_DEPTH, ROOT_BIT,
+    SENTINEL_NODE,
+};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+// Two independent tries of up to 8 nodes each, sharing one pair of 16 lane vectors: the low trie
+// in lanes 0 to 7 and the high one in lanes 8 to 15, with the high trie's parent pointers moved
+// up by 8. Neither half points into the other, so masking the roots down to one half keeps every
+// match inside it. Half the memory of two `ByteTrie16`s, for the same lookups.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct DualTrie8 {
+    nodes: VectorOf<16>,
+    edges: VectorOf<16>,
+}
+
+impl DualTrie8 {
+    // Panics if `try_new` would return an error.
+    pub fn new(low: &BTreeSet<Edge>, high: &BTreeSet<Edge>) -> Self {
+        Self::try_new(low, high).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Checks each trie the same way `ByteTrie::try_new` does, with 8 lanes apiece.
+    pub fn try_new(low: &BTreeSet<Edge>, high: &BTreeSet<Edge>) -> Result<Self, BuildError> {
+        let mut packed_edges = [0u8; 16];
+        let mut packed_nodes = [SENTINEL_NODE; 16];
+        for (half, edges) in IntoIterator::into_iter([low, high]).enumerate() {
+            if edges.len() > 8 {
+                return Err(BuildError::TooManyNodes(edges.len()));
+            }
+            let sorted: Vec<Edge> = edges.iter().copied().collect();
+            let (half_edges, half_nodes) = build_tree::<8, u8>(&sorted, MAX_DEPTH)?;
+            let offset = half * 8;
+            packed_edges[offset..offset + 8].copy_from_slice(&half_edges);
+            for (slot, &node) in packed_nodes[offset..offset + 8].iter_mut().zip(&half_nodes) {
+                // The parent pointer is the low bits, and flags don't carry into them.
+                *slot = if node & ROOT_BIT == 0 { node + offset as u8 } else { node };
+            }
+        }
+        Ok(Self { nodes: VectorOf::<16>::from_slice(&packed_nodes), edges: VectorOf::<16>::from_slice(&packed_edges) })
+    }
+
+    // Same as `ByteTrie::traverse` on the low trie when `which` is 0 and the high one when it's 1.
+    // Ranks count from the start of that trie. Panics on any other `which`.
+    pub fn traverse(&self, which: usize, query: &[u8; 8], query_len: usize) -> Lookup {
+        assert!(which < 2, "a DualTrie8 only has tries 0 and 1");
+        debug_assert_padded(query, query_len);
+        let mut half = [0u8; 16];
+        half[which * 8..which * 8 + 8].copy_from_slice(&[0xFF; 8]);
+        let half = VectorOf::<16>::from_slice(&half);
+
+        let lanes = Lanes::new(NodeBytes(self.nodes), self.edges);
+        let lanes = Lanes { roots: lanes.roots & half, ..lanes };
+        match match_path(&lanes, query, query_len) {
+            // Clearing the other half's nodes takes its flags out of the ranks.
+            Some(match_mask) => classify(self.nodes & half, match_mask),
+            None => Lookup::NoSuchPath,
+        }
+    }
+}
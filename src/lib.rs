@@ -1,95 +1,1579 @@
-#![feature(min_const_generics)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "portable_simd", feature(portable_simd))]
+
+extern crate alloc;
 
 #[cfg(test)]
 mod tests;
+mod arena;
+mod builder;
+mod cursor;
+mod depth;
+mod dual;
+mod linked;
+mod macros;
+mod map;
+#[cfg(all(
+    feature = "neon",
+    target_arch = "aarch64",
+    not(any(feature = "scalar", feature = "portable_simd")),
+))]
+mod neon;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "portable_simd")]
+mod portable;
+mod remap;
+#[cfg(any(
+    test,
+    feature = "scalar",
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    not(any(
+        feature = "packed_simd",
+        feature = "portable_simd",
+        all(feature = "neon", target_arch = "aarch64"),
+    )),
+))]
+mod scalar;
+#[cfg(feature = "serde")]
+mod serialize;
+mod slow;
+mod simd;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod wide;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(any(feature = "scalar", feature = "portable_simd")),
+))]
+mod wasm;
+
+use simd::{Bitmask, Mask, NodeBytes, NodeLanes, NodeOf, NodeWord, NodesOf, SplitNodes, Vector, VectorOf};
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::vec;
+use core::cmp::Reverse;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::FromIterator;
+use core::ops::Bound;
 
-use packed_simd::{
-    u8x16,
-};
-use std::collections::{HashMap, BTreeSet};
+pub use arena::{TrieArena, TrieArena16, TrieArena32, TrieArena64};
+pub use builder::{NodeId, TrieBuilder};
+pub use cursor::{Cursor, CursorState};
+pub use depth::ByteTrieDepth;
+pub use dual::DualTrie8;
+pub use linked::{LinkedTrie, LinkedTrie16, LinkedTrie32, LinkedTrie64};
+#[doc(hidden)]
+pub use macros::EdgeList;
+pub use map::{ByteMap, ByteMap16, ByteMap32, ByteMap64};
+pub use remap::{RemappedTrie, RemappedTrie16, RemappedTrie32, RemappedTrie64};
+pub use simd::{LaneCount, SupportedLaneCount};
+pub use slow::{SlowTrie, Trie, Trie16, Trie32, Trie64};
+pub use wide::{WideEdge, WideTrie, WideTrie16, WideTrie32, WideTrie64};
 
-pub struct ByteTrie16 {
-    // [ 0: no_parent? ] [ 1: has value? ] [ 2: has branch? ] [ 3: unused ] [ 4-8: parent pointer ]
-    nodes: u8x16,
+// Two tries are equal when their lanes are, so the same tree built with different edge numbers
+// compares unequal.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ByteTrie<const LANES: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // [ 7: root? ] [ 6: has value? ] [ 5: has branch? ] [ 0-4: parent pointer ], with the masks
+    // for each below. 64 lane tries keep the rest of the parent pointer in a second vector.
+    nodes: NodesOf<LANES>,
     // Label of incoming edge
-    edges: u8x16,
+    edges: VectorOf<LANES>,
+    // `max_depth`, worked out when the lanes are set so lookups can turn away longer queries
+    // without shuffling.
+    max_depth: u8,
 }
 
-impl ByteTrie16 {
+pub type ByteTrie16 = ByteTrie<16>;
+pub type ByteTrie32 = ByteTrie<32>;
+pub type ByteTrie64 = ByteTrie<64>;
+
+// The parts of a node byte. A root's parent pointer is always zero.
+const ROOT_BIT: u8 = 1 << 7;
+const VALUE_BIT: u8 = 1 << 6;
+const BRANCH_BIT: u8 = 1 << 5;
+// The parent pointer gets whatever the flags leave, which is enough to address 32 lanes. Past
+// that, the rest of the pointer goes in the byte above, as in `NodeWord`.
+const PARENT_BITS: u32 = 5;
+const PARENT_MASK: u8 = (1 << PARENT_BITS) - 1;
+
+// Longest path `traverse_deep` can follow.
+const MAX_DEPTH: usize = 16;
+
+// Node byte for lanes that aren't part of the trie: the root flag, no value or branch, and an all
+// ones parent pointer. Roots always have a zero parent pointer, so a root flag with a nonzero
+// pointer can't be mistaken for a real node. That's what keeps unused lanes from matching: their
+// label is 0, which is also a perfectly good label for a real edge, but `Lanes::new` counts them as
+// neither roots nor children. The pointer itself doesn't matter, which is just as well, since
+// lane 31 is real in a 32 lane trie.
+const SENTINEL_NODE: u8 = 0b1001_1111;
+const SENTINEL_WORD: u16 = SENTINEL_NODE as u16;
+
+// Whether a node word has `flag`, which like all of the flags is in its low byte.
+const fn has_flag(word: u16, flag: u8) -> bool {
+    word as u8 & flag != 0
+}
+
+// The parent pointer in a node word.
+const fn word_parent(word: u16) -> usize {
+    ((word & PARENT_MASK as u16) | (word >> 8) << PARENT_BITS) as usize
+}
+
+// Inverse of `word_parent`, for a node with no flags.
+const fn parent_word(parent: usize) -> u16 {
+    (parent as u16 & PARENT_MASK as u16) | ((parent >> PARENT_BITS) as u16) << 8
+}
+
+impl<const LANES: usize> ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // A trie with every lane unused, so nothing matches. Same as `new` with no edges.
+    pub fn empty() -> Self {
+        Self::from_slices(&[0; LANES], &[SENTINEL_WORD; LANES])
+    }
+
+    // Panics if `try_new` would return an error.
     pub fn new(edges: &BTreeSet<Edge>) -> Self {
-        assert!(edges.len() <= 16);
-        let (packed_edges, packed_nodes) = build_tree(edges, 8);
-        let edges = u8x16::from(packed_edges);
-        let nodes = u8x16::from(packed_nodes);
-        Self { edges, nodes }
+        Self::try_new(edges).unwrap_or_else(|e| panic!("{}", e))
     }
 
-    fn match_bitsets(&self, query: &[u8; 8]) -> u8x16 {
-        let zero = u8x16::splat(0);
-        let mut out = zero;
-        for i in 0..8 {
-            let label = u8x16::splat(query[i]);
-            let bitset = u8x16::splat(1 << i);
-            out |= self.edges.eq(label).select(bitset, zero);
+    pub fn try_new(edges: &BTreeSet<Edge>) -> Result<Self, BuildError> {
+        if edges.len() > LANES {
+            return Err(BuildError::TooManyNodes(edges.len()));
         }
-        out
+        let sorted: Vec<Edge> = edges.iter().copied().collect();
+        let (packed_edges, packed_nodes) = build_tree::<LANES, _>(&sorted, MAX_DEPTH)?;
+        Ok(Self::from_packed(packed_edges, packed_nodes))
+    }
+
+    // Same as `try_new`, but reuses the temporaries in `scratch` from earlier builds instead of
+    // allocating its own, for code that rebuilds tries in a loop. On error, `self` is left as it
+    // was.
+    pub fn rebuild_into(&mut self, edges: &BTreeSet<Edge>, scratch: &mut BuildScratch) -> Result<(), BuildError> {
+        if edges.len() > LANES {
+            return Err(BuildError::TooManyNodes(edges.len()));
+        }
+        scratch.sorted.clear();
+        scratch.sorted.extend(edges.iter().copied());
+        let (packed_edges, packed_nodes) = build_tree_with::<LANES, _>(&scratch.sorted, MAX_DEPTH, &mut scratch.stack)?;
+        *self = Self::from_packed(packed_edges, packed_nodes);
+        Ok(())
+    }
+
+    // Same as `new`, for edges that are already in `BTreeSet` order, i.e. sorted by parent and then
+    // label, so they don't have to be copied into one. The order is only checked in debug builds;
+    // out of order edges can come out as orphans, with siblings missing, or with duplicate sibling
+    // labels let through.
+    pub fn from_sorted_edges(edges: &[Edge]) -> Self {
+        debug_assert!(edges.windows(2).all(|w| w[0] < w[1]), "edges aren't sorted");
+        if edges.len() > LANES {
+            panic!("{}", BuildError::TooManyNodes(edges.len()));
+        }
+        let (packed_edges, packed_nodes) =
+            build_tree::<LANES, _>(edges, MAX_DEPTH).unwrap_or_else(|e| panic!("{}", e));
+        Self::from_packed(packed_edges, packed_nodes)
+    }
+
+    // Same as `try_new`, but only lets the trie use its first `limit` lanes, for callers tuned for
+    // smaller tries. Panics if `limit` is more than `LANES`.
+    pub fn with_capacity_limit(edges: &BTreeSet<Edge>, limit: usize) -> Result<Self, BuildError> {
+        assert!(limit <= LANES, "capacity limit {} is more than {} lanes", limit, LANES);
+        let highest = edges.iter().map(|e| e.number + 1).max().unwrap_or(0);
+        let needed = edges.len().max(highest);
+        if needed > limit {
+            return Err(BuildError::TooManyNodes(needed));
+        }
+        Self::try_new(edges)
+    }
+
+    // Builds a trie from `(parent, label, has_value, has_branch)` tuples, numbering the edges in the
+    // order given. Each parent has to be the index of an earlier edge.
+    pub fn from_edges_auto(edges: &[(Option<usize>, u8, bool, bool)]) -> Result<Self, BuildError> {
+        let mut numbered = BTreeSet::new();
+        for (number, &(parent, label, has_value, has_branch)) in edges.iter().enumerate() {
+            if let Some(parent) = parent.filter(|&p| p >= number) {
+                return Err(BuildError::ParentAfterChild { number, parent });
+            }
+            numbered.insert(Edge { parent, label, number, has_value, has_branch });
+        }
+        Self::try_new(&numbered)
+    }
+
+    // Same as `from_keys`, over the UTF-8 bytes of each key. The depth limit counts bytes, not
+    // characters, so a key that encodes to more than 16 bytes is `DepthExceeded` rather than being
+    // cut off partway through a character.
+    pub fn from_strs<S: AsRef<str>, I: IntoIterator<Item = S>>(keys: I) -> Result<Self, BuildError> {
+        Self::from_keys(keys.into_iter().map(|key| key.as_ref().as_bytes().to_vec()))
+    }
+
+    // Same as `from_keys`, but when the keys need more edges than there are lanes, drops whole keys,
+    // lowest priority first, until the rest fit, and returns the dropped ones in the order they
+    // went. Of keys with the same priority, the larger goes first, and a key given twice keeps its
+    // higher priority. Only keys whose end no kept key runs past get dropped, since dropping a
+    // prefix of a kept key wouldn't free any edges: it waits until the keys past it have gone, if
+    // they go at all. Empty and too long keys are errors whether or not they'd be dropped.
+    pub fn from_keys_capped(keys: Vec<(Vec<u8>, u32)>) -> Result<(Self, Vec<Vec<u8>>), BuildError> {
+        let mut priorities: BTreeMap<Vec<u8>, u32> = BTreeMap::new();
+        for (key, priority) in keys {
+            if key.is_empty() {
+                return Err(BuildError::EmptyKey);
+            }
+            if key.len() > MAX_DEPTH {
+                return Err(BuildError::DepthExceeded(key.len()));
+            }
+            let slot = priorities.entry(key).or_insert(priority);
+            *slot = (*slot).max(priority);
+        }
+
+        // Each edge is a distinct prefix, so count how many kept keys run through each one.
+        let mut uses: BTreeMap<&[u8], usize> = BTreeMap::new();
+        for key in priorities.keys() {
+            for len in 1..=key.len() {
+                *uses.entry(&key[..len]).or_insert(0) += 1;
+            }
+        }
+        let mut order: Vec<(u32, &Vec<u8>)> = priorities.iter().map(|(key, &p)| (p, key)).collect();
+        order.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(a.1)));
+        let index: BTreeMap<&[u8], usize> = order.iter().enumerate().map(|(i, &(_, key))| (&key[..], i)).collect();
+
+        // Keys come off the queue in drop order. One that's still a prefix of a kept key waits
+        // aside until its last edge is down to just its own use, and then goes back in.
+        let mut queue: BinaryHeap<Reverse<usize>> = (0..order.len()).map(Reverse).collect();
+        let mut waiting = vec![false; order.len()];
+        let mut is_dropped = vec![false; order.len()];
+        let mut dropped = vec![];
+        while uses.len() > LANES {
+            let Reverse(i) = queue.pop().expect("the longest kept key always frees an edge");
+            let key = order[i].1;
+            if uses[&key[..]] > 1 {
+                waiting[i] = true;
+                continue;
+            }
+            for len in 1..=key.len() {
+                let count = uses.get_mut(&key[..len]).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    uses.remove(&key[..len]);
+                } else if *count == 1 {
+                    if let Some(&j) = index.get(&key[..len]).filter(|&&j| waiting[j]) {
+                        waiting[j] = false;
+                        queue.push(Reverse(j));
+                    }
+                }
+            }
+            is_dropped[i] = true;
+            dropped.push(key.clone());
+        }
+        let kept = order.iter().enumerate().filter(|&(i, _)| !is_dropped[i]).map(|(_, &(_, key))| key.clone());
+        Ok((Self::from_keys(kept)?, dropped))
+    }
+
+    // Builds a trie with a value at the end of each key, sharing edges between common prefixes.
+    // Value ranks follow the sorted order of the keys.
+    pub fn from_keys<I: IntoIterator<Item = Vec<u8>>>(keys: I) -> Result<Self, BuildError> {
+        let keys: BTreeSet<Vec<u8>> = keys.into_iter().collect();
+
+        // Since the keys are sorted, each one only has to add the suffix it doesn't share with
+        // the previous key, and numbering edges as we create them walks the tree in preorder.
+        // That puts the value ranks in key order.
+        let mut edges: Vec<Edge> = vec![];
+        let mut path: Vec<usize> = vec![];
+        let mut prev_key: &[u8] = &[];
+        for key in &keys {
+            if key.is_empty() {
+                return Err(BuildError::EmptyKey);
+            }
+            if key.len() > MAX_DEPTH {
+                return Err(BuildError::DepthExceeded(key.len()));
+            }
+            let shared = key.iter().zip(prev_key).take_while(|(a, b)| a == b).count();
+            path.truncate(shared);
+            for &label in &key[shared..] {
+                let number = edges.len();
+                edges.push(Edge {
+                    parent: path.last().copied(),
+                    label,
+                    number,
+                    has_value: false,
+                    has_branch: false,
+                });
+                path.push(number);
+            }
+            edges[*path.last().unwrap()].has_value = true;
+            prev_key = key;
+        }
+
+        Self::try_new(&edges.into_iter().collect())
     }
 
+    // Builds a trie with every path in either input. A node on both gets the flags from each, and
+    // edges are renumbered in preorder like `from_keys`, so ranks follow the merged trie rather
+    // than either input.
+    pub fn merge(a: &Self, b: &Self) -> Result<Self, BuildError> {
+        let mut paths = a.paths();
+        for (path, (has_value, has_branch)) in b.paths() {
+            let flags = paths.entry(path).or_default();
+            flags.0 |= has_value;
+            flags.1 |= has_branch;
+        }
+        Self::from_paths(&paths)
+    }
+
+    // A copy with a path for `key`, reusing whatever prefix of it is already there, and the given
+    // flags added to the node at its end. Edges are renumbered in preorder like `from_keys`, so
+    // ranks may shift.
+    pub fn with_key(&self, key: &[u8], has_value: bool, has_branch: bool) -> Result<Self, BuildError> {
+        if key.is_empty() {
+            return Err(BuildError::EmptyKey);
+        }
+        if key.len() > MAX_DEPTH {
+            return Err(BuildError::DepthExceeded(key.len()));
+        }
+        let mut paths = self.paths();
+        for end in 1..key.len() {
+            paths.entry(key[..end].to_vec()).or_default();
+        }
+        let flags = paths.entry(key.to_vec()).or_default();
+        flags.0 |= has_value;
+        flags.1 |= has_branch;
+        Self::from_paths(&paths)
+    }
+
+    // A copy without the value at the end of `key`, or the same trie if there isn't one. Nodes
+    // left with no value, branch, or children are pruned, all the way up if need be, and edges are
+    // renumbered in preorder like `from_keys`, so ranks may shift.
+    pub fn without_key(&self, key: &[u8]) -> Self {
+        let mut paths = self.paths();
+        match paths.get_mut(key) {
+            Some((has_value, _)) if *has_value => *has_value = false,
+            _ => return *self,
+        }
+
+        for end in (1..=key.len()).rev() {
+            let path = &key[..end];
+            let after = (Bound::Excluded(path), Bound::Unbounded);
+            let has_children = paths.range::<[u8], _>(after).next().is_some_and(|(p, _)| p.starts_with(path));
+            if paths[path] != (false, false) || has_children {
+                break;
+            }
+            paths.remove(path);
+        }
+        Self::from_paths(&paths).expect("removing a key can't make the trie any bigger")
+    }
+
+    // The path to every node along with its `(has_value, has_branch)` flags.
+    fn paths(&self) -> BTreeMap<Vec<u8>, (bool, bool)> {
+        let (edges, nodes) = self.words();
+        let mut paths = BTreeMap::new();
+        for (i, &node) in nodes.iter().enumerate() {
+            if node == SENTINEL_WORD {
+                continue;
+            }
+            let mut path: Vec<u8> = ancestors(&nodes, i).map(|a| edges[a]).collect();
+            path.reverse();
+            paths.insert(path, (has_flag(node, VALUE_BIT), has_flag(node, BRANCH_BIT)));
+        }
+        paths
+    }
+
+    // Inverse of `paths`, numbering edges in preorder. Every prefix of a path has to be in there
+    // too.
+    fn from_paths(paths: &BTreeMap<Vec<u8>, (bool, bool)>) -> Result<Self, BuildError> {
+        // Sorting puts each path right after its parent's, so the parent's number is always known
+        // by the time a child needs it.
+        let mut numbers: BTreeMap<&[u8], usize> = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+        for (number, (path, &(has_value, has_branch))) in paths.iter().enumerate() {
+            let (&label, parent_path) = path.split_last().unwrap();
+            edges.insert(Edge {
+                parent: numbers.get(parent_path).copied(),
+                label,
+                number,
+                has_value,
+                has_branch,
+            });
+            numbers.insert(path, number);
+        }
+        Self::try_new(&edges)
+    }
+
+    // Looks up the first `query_len` bytes of `query`. The rest have to be zero, which is only
+    // checked in debug builds: nonzero padding usually means a key got truncated without its
+    // length being updated.
     pub fn traverse(&self, query: &[u8; 8], query_len: usize) -> Lookup {
-        let zero = u8x16::splat(0);
-        let edge_matches = self.match_bitsets(query);
-
-        let root_byte = 0b1000_0000;
-        let matches0 = (self.nodes & u8x16::splat(root_byte)).eq(zero).select(zero, edge_matches);
-        let matches1 = (matches0.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-        let matches2 = (matches1.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-        let matches3 = (matches2.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-        let matches4 = (matches3.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-        let matches5 = (matches4.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-        let matches6 = (matches5.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-        let matches7 = (matches6.shuffle1_dyn(self.nodes) << 1) & edge_matches;
-
-        let state = match query_len {
-            1 => matches0,
-            2 => matches1,
-            3 => matches2,
-            4 => matches3,
-            5 => matches4,
-            6 => matches5,
-            7 => matches6,
-            8 => matches7,
-            _ => panic!("Invalid query len"),
+        debug_assert_padded(query, query_len);
+        if query_len > self.max_depth as usize {
+            return Lookup::NoSuchPath;
+        }
+        traverse(&Lanes::new(self.nodes, self.edges), query, query_len)
+    }
+
+    // Same as `traverse`, for code that also uses `traverse_ge` and wants to say which one it
+    // means.
+    pub fn traverse_exact(&self, query: &[u8; 8], query_len: usize) -> Lookup {
+        self.traverse(query, query_len)
+    }
+
+    // Experimental: follows all but the last query byte exactly, like `traverse`, and then takes
+    // the child with the smallest label at least the last byte, returning that label and what
+    // `traverse` would for it. `None` if the prefix isn't a path or no child's label is big
+    // enough. Labels compare unsigned, so for signed labels flip the top bit (`x ^ 0x80`) on both
+    // the keys and the query.
+    pub fn traverse_ge(&self, query: &[u8; 8], query_len: usize) -> Option<(u8, Lookup)> {
+        if query_len == 0 || query_len > 8 {
+            return None;
+        }
+        debug_assert_padded(query, query_len);
+        let parent = self.prefix_end(query, query_len - 1)?;
+
+        // Nothing past the prefix is a vector operation anymore, so work on the bytes.
+        let (edges, nodes) = self.words();
+        let lane = (0..LANES)
+            .filter(|&i| is_child_of(nodes[i], parent) && edges[i] >= query[query_len - 1])
+            .min_by_key(|&i| edges[i])?;
+        Some((edges[lane], lookup_for_lane(&nodes, lane)))
+    }
+
+    // `traverse` along with the length of the query prefix the lookup is for, which is all of it
+    // when the query is a path, whether or not anything ends there, and 0 when it isn't.
+    pub fn traverse_with_depth(&self, query: &[u8; 8], query_len: usize) -> (Lookup, usize) {
+        match self.traverse(query, query_len) {
+            Lookup::NoSuchPath => (Lookup::NoSuchPath, 0),
+            lookup => (lookup, query_len),
+        }
+    }
+
+    // Same as `traverse`, but a node with both a value and a branch comes back as just the one
+    // `precedence` picks, for callers that treat one as overriding the other.
+    pub fn traverse_with_precedence(&self, query: &[u8; 8], query_len: usize, precedence: Precedence) -> Lookup {
+        match (self.traverse(query, query_len), precedence) {
+            (Lookup::ValueAndBranch(value_rank, _), Precedence::Value) => Lookup::Value(value_rank),
+            (Lookup::ValueAndBranch(_, branch_rank), Precedence::Branch) => Lookup::Branch(branch_rank),
+            (lookup, _) => lookup,
+        }
+    }
+
+    // A cursor at the root, for following a query a byte at a time with `Cursor::step`.
+    pub fn cursor(&self) -> Cursor<LANES> {
+        Cursor::new(self)
+    }
+
+    // Same as `traverse`, but ASCII letters in the query match either case. Only the query gets
+    // folded, so this only works on tries whose labels are already lowercase, e.g. from keys that
+    // went through `to_ascii_lowercase`.
+    pub fn traverse_ci(&self, query: &[u8; 8], query_len: usize) -> Lookup {
+        self.traverse(&query.map(|byte| byte.to_ascii_lowercase()), query_len)
+    }
+
+    // Same as running `traverse` on each query and writing the results to `out`, which must be the
+    // same length, but only does the query-independent setup once.
+    pub fn traverse_batch(&self, queries: &[([u8; 8], usize)], out: &mut [Lookup]) {
+        assert_eq!(queries.len(), out.len());
+        let lanes = Lanes::new(self.nodes, self.edges);
+        for ((query, query_len), slot) in queries.iter().zip(out) {
+            debug_assert_padded(query, *query_len);
+            *slot = match *query_len > self.max_depth as usize {
+                true => Lookup::NoSuchPath,
+                false => traverse(&lanes, query, *query_len),
+            };
+        }
+    }
+
+    // Same as `traverse` on each of four queries, but with the lookups interleaved a level at a
+    // time, so one query's shuffle doesn't have to wait on the one before it. Out of order CPUs
+    // already overlap separate `traverse` calls, though, and this can't stop early on misses, so
+    // compare `traverse/traverse4` with `traverse/sequential4` on the target before switching.
+    pub fn traverse4(&self, queries: &[[u8; 8]; 4], lens: &[usize; 4]) -> [Lookup; 4] {
+        for (query, &query_len) in queries.iter().zip(lens) {
+            debug_assert_padded(query, query_len);
+        }
+        traverse4(&Lanes::new(self.nodes, self.edges), queries, lens)
+    }
+
+    // Same as `traverse` on each of `tries`, for finding which of a set of tries has a key. Each
+    // trie's shuffles depend on its own lanes, so this can't share much more than the query
+    // between them: compare `gather/traverse_gather` with `gather/loop` before expecting a win.
+    pub fn traverse_gather<const N: usize>(tries: &[Self; N], query: &[u8; 8], query_len: usize) -> [Lookup; N] {
+        debug_assert_padded(query, query_len);
+        core::array::from_fn(|i| match query_len > tries[i].max_depth as usize {
+            true => Lookup::NoSuchPath,
+            false => traverse(&Lanes::new(tries[i].nodes, tries[i].edges), query, query_len),
+        })
+    }
+
+    // The value rank and length of the longest prefix of the query that ends at a value, whether
+    // or not it has a branch too. Queries longer than 8 don't match, like in `traverse`.
+    pub fn longest_prefix_match(&self, query: &[u8; 8], query_len: usize) -> Option<(u8, usize)> {
+        debug_assert_padded(query, query_len);
+        longest_prefix_match(&Lanes::new(self.nodes, self.edges), query, query_len)
+    }
+
+    // The value rank at the end of the query, or else at the closest node above it with a value.
+    // Unlike `longest_prefix_match`, the whole query has to be a path in the trie: only then does
+    // this walk up its parents, so a query that runs off the trie is `None`.
+    pub fn value_or_ancestor(&self, query: &[u8; 8], query_len: usize) -> Option<u8> {
+        debug_assert_padded(query, query_len);
+        let match_mask = match_path(&Lanes::new(self.nodes, self.edges), query, query_len)?;
+        if match_mask.count_ones() == 0 {
+            return None;
+        }
+        let (_, nodes) = self.words();
+        let lane = ancestors(&nodes, match_mask.trailing_zeros() as usize).find(|&a| has_flag(nodes[a], VALUE_BIT))?;
+        Some(nodes[..lane].iter().filter(|&&n| has_flag(n, VALUE_BIT)).count() as u8)
+    }
+
+    // Whether `traverse` finds a value, with or without a branch.
+    pub fn contains_key(&self, query: &[u8; 8], query_len: usize) -> bool {
+        matches!(self.traverse(query, query_len), Lookup::Value(_) | Lookup::ValueAndBranch(..))
+    }
+
+    // The value rank `traverse` finds, with or without a branch, or `None` if the query doesn't
+    // end at a value.
+    pub fn value_rank(&self, query: &[u8; 8], query_len: usize) -> Option<u8> {
+        match self.traverse(query, query_len) {
+            Lookup::Value(rank) | Lookup::ValueAndBranch(rank, _) => Some(rank),
+            _ => None,
+        }
+    }
+
+    // The first thing `traverse` works out: bit `i` of lane `j` is set when `query[i]` equals the
+    // label on lane `j`, regardless of where the lane sits in the trie. All 8 bytes are compared,
+    // so bits past the query's length have to be masked off, and unused lanes carry label 0, so
+    // they match any zero byte; check `node_label` before trusting a lane. Following the matches
+    // down from the roots is up to the caller.
+    pub fn edge_matches(&self, query: &[u8; 8]) -> [u8; LANES] {
+        let mut out = [0u8; LANES];
+        match_bitsets(self.edges, query).write_to_slice(&mut out);
+        out
+    }
+
+    // Length of the longest prefix of the query that's a path in the trie, whether or not anything
+    // ends there, or 0 if not even the first byte is. Only the first 8 bytes count, so longer
+    // queries get at most 8.
+    pub fn prefix_len(&self, query: &[u8; 8], query_len: usize) -> usize {
+        debug_assert_padded(query, query_len);
+        let lanes = Lanes::new(self.nodes, self.edges);
+        let levels = match_levels(&lanes, match_bitsets(lanes.edges, query));
+        (1..=query_len.min(8))
+            .rev()
+            .find(|&len| level_mask(levels[len - 1], len).count_ones() > 0)
+            .unwrap_or(0)
+    }
+
+    // `traverse` if the query is a path in the trie, and otherwise the position of the first
+    // query byte with no edge to follow, for telling why a lookup missed. Like `traverse`, only
+    // 8 bytes fit, so a longer query that matches all of them breaks at position 8.
+    pub fn explain(&self, query: &[u8; 8], query_len: usize) -> Result<Lookup, usize> {
+        match self.prefix_len(query, query_len) {
+            matched if matched < query_len => Err(matched),
+            _ => Ok(self.traverse(query, query_len)),
+        }
+    }
+
+    // Whether the query is a path in the trie, whether or not anything ends there. The empty path
+    // is always there, and, like `traverse`, anything longer than 8 isn't.
+    pub fn contains_prefix(&self, query: &[u8; 8], query_len: usize) -> bool {
+        debug_assert_padded(query, query_len);
+        if query_len == 0 {
+            return true;
+        }
+        match match_path(&Lanes::new(self.nodes, self.edges), query, query_len) {
+            Some(match_mask) => match_mask.count_ones() > 0,
+            None => false,
+        }
+    }
+
+    // Every path that ends at a value or branch, along with what `traverse` returns for it, in
+    // lane order.
+    pub fn keys(&self) -> impl Iterator<Item = (Vec<u8>, Lookup)> {
+        let (edges, nodes) = self.words();
+        let mut keys = vec![];
+        for i in 0..LANES {
+            let lookup = lookup_for_lane(&nodes, i);
+            if lookup == Lookup::Exists {
+                continue;
+            }
+            let mut path: Vec<u8> = ancestors(&nodes, i).map(|a| edges[a]).collect();
+            path.reverse();
+            keys.push((path, lookup));
+        }
+        keys.into_iter()
+    }
+
+    // The value rank and path of every value, in rank order, which is also lane order.
+    pub fn value_entries(&self) -> Vec<(u8, Vec<u8>)> {
+        self.keys()
+            .filter_map(|(path, lookup)| match lookup {
+                Lookup::Value(rank) | Lookup::ValueAndBranch(rank, _) => Some((rank, path)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // The path to every node with a branch, along with its branch rank, in lane order.
+    pub fn branches(&self) -> impl Iterator<Item = (Vec<u8>, u8)> {
+        self.keys().filter_map(|(path, lookup)| match lookup {
+            Lookup::Branch(rank) | Lookup::ValueAndBranch(_, rank) => Some((path, rank)),
+            _ => None,
+        })
+    }
+
+    // A Graphviz graph of the trie, with a node per lane labeled with its lane and edge label, and
+    // every root hanging off one extra point. Values are double circles and branches are dashed.
+    pub fn to_dot(&self) -> String {
+        use core::fmt::Write;
+
+        let mut dot = String::from("digraph {\n    root [shape=point];\n");
+        for edge in self.edge_vec() {
+            let shape = if edge.has_value { "doublecircle" } else { "circle" };
+            let style = if edge.has_branch { "dashed" } else { "solid" };
+            let n = edge.number;
+            // Writing to a `String` can't fail.
+            let _ = writeln!(dot, "    n{} [label=\"{}: {}\", shape={}, style={}];", n, n, edge.label, shape, style);
+            let _ = match edge.parent {
+                Some(p) => writeln!(dot, "    n{} -> n{};", p, n),
+                None => writeln!(dot, "    root -> n{};", n),
+            };
+        }
+        dot.push('}');
+        dot
+    }
+
+    // The edges the trie was built from, in number order. Lanes are numbers, so these are exactly
+    // the edges passed to `new`.
+    pub fn iter_edges(&self) -> impl Iterator<Item = Edge> {
+        self.edge_vec().into_iter()
+    }
+
+    // Every value and branch at the end of a path that fits the pattern, in lane order. A `None` in
+    // the pattern matches any label at that position, so more than one path can fit. Patterns
+    // longer than 8 don't match anything, like in `traverse`.
+    pub fn traverse_wildcard(&self, query: &[Option<u8>; 8], query_len: usize) -> Vec<Lookup> {
+        if query_len == 0 || query_len > 8 {
+            return vec![];
+        }
+        self.lookups_in(self.wildcard_ends(query, query_len))
+    }
+
+    // Every value and branch at the end of a path that's the query with at most one byte swapped
+    // for another, in lane order. That's `traverse_wildcard` with each position in turn left open,
+    // but with the ends of all of them combined before any lookups come out, so a path that fits
+    // more than one (the exact match fits all of them) is only there once.
+    pub fn traverse_fuzzy1(&self, query: &[u8; 8], query_len: usize) -> Vec<Lookup> {
+        debug_assert_padded(query, query_len);
+        if query_len == 0 || query_len > 8 {
+            return vec![];
+        }
+        let mut ends = [false; LANES];
+        for open in 0..query_len {
+            let mut pattern = query.map(Some);
+            pattern[open] = None;
+            for (end, matched) in ends.iter_mut().zip(&self.wildcard_ends(&pattern, query_len)) {
+                *end |= matched;
+            }
+        }
+        self.lookups_in(ends)
+    }
+
+    // Which lanes end a path that fits the pattern, for a length from 1 to 8.
+    fn wildcard_ends(&self, query: &[Option<u8>; 8], query_len: usize) -> [bool; LANES] {
+        let zero = VectorOf::<LANES>::splat(0);
+        let mut edge_matches = zero;
+        for (i, byte) in query.iter().enumerate() {
+            let bitset = VectorOf::<LANES>::splat(1 << i);
+            edge_matches |= match byte {
+                Some(byte) => self.edges.simd_eq(VectorOf::<LANES>::splat(*byte)).select(bitset, zero),
+                None => bitset,
+            };
+        }
+        let levels = match_levels(&Lanes::new(self.nodes, self.edges), edge_matches);
+        let mut level = [0u8; LANES];
+        levels[query_len - 1].write_to_slice(&mut level);
+        level.map(|bits| bits & (1 << (query_len - 1)) != 0)
+    }
+
+    // The value or branch at each of the `ends`, in lane order, skipping lanes with neither.
+    fn lookups_in(&self, ends: [bool; LANES]) -> Vec<Lookup> {
+        let (_, nodes) = self.words();
+        (0..LANES)
+            .filter(|&i| ends[i])
+            .map(|i| lookup_for_lane(&nodes, i))
+            .filter(|lookup| *lookup != Lookup::Exists)
+            .collect()
+    }
+
+    // The value ranks of every value at or below the end of `prefix`, in rank order. The empty
+    // prefix covers the whole trie, and prefixes longer than 8 don't match, like in `traverse`.
+    pub fn values_with_prefix(&self, prefix: &[u8; 8], prefix_len: usize) -> Vec<u8> {
+        self.ranks_under(prefix, prefix_len, VALUE_BIT, 0)
+    }
+
+    // The branch ranks of every branch strictly below the end of `prefix`, in rank order, so a
+    // branch at the end of the prefix itself isn't included. Otherwise the same as
+    // `values_with_prefix`.
+    pub fn branches_under_prefix(&self, prefix: &[u8; 8], prefix_len: usize) -> Vec<u8> {
+        self.ranks_under(prefix, prefix_len, BRANCH_BIT, 1)
+    }
+
+    // The labels on the edges out of the end of `prefix`, in ascending order: the bytes that can
+    // come next on the way to a key, e.g. for only accepting keystrokes that lead somewhere. The
+    // empty prefix gives the labels out of the root, and a prefix that doesn't match gives none.
+    pub fn next_bytes(&self, prefix: &[u8; 8], prefix_len: usize) -> Vec<u8> {
+        debug_assert_padded(prefix, prefix_len);
+        let parent = match self.prefix_end(prefix, prefix_len) {
+            Some(parent) => parent,
+            None => return vec![],
+        };
+        let (edges, nodes) = self.words();
+        let mut labels: Vec<u8> = (0..LANES).filter(|&i| is_child_of(nodes[i], parent)).map(|i| edges[i]).collect();
+        // Siblings never share a label, so there's nothing to dedup.
+        labels.sort_unstable();
+        labels
+    }
+
+    // Ranks among the nodes with `flag` set of those under the end of `prefix`, skipping the ones
+    // fewer than `min_depth` edges below it.
+    fn ranks_under(&self, prefix: &[u8; 8], prefix_len: usize, flag: u8, min_depth: usize) -> Vec<u8> {
+        debug_assert_padded(prefix, prefix_len);
+        let start = match self.prefix_end(prefix, prefix_len) {
+            Some(start) => start,
+            None => return vec![],
+        };
+
+        // Lanes aren't in DFS order, so check each node's ancestry instead of taking a range. The
+        // root isn't in any lane, so everything is at least one edge below it.
+        let (_, nodes) = self.words();
+        let mut ranks = vec![];
+        let mut num_flagged = 0;
+        for (i, &node) in nodes.iter().enumerate() {
+            if node == SENTINEL_WORD || !has_flag(node, flag) {
+                continue;
+            }
+            let under = match start {
+                None => true,
+                Some(start) => ancestors(&nodes, i).skip(min_depth).any(|a| a == start),
+            };
+            if under {
+                ranks.push(num_flagged);
+            }
+            num_flagged += 1;
+        }
+        ranks
+    }
+
+    // The lane `prefix` ends at, `None` inside for the empty prefix, which ends at the root, or
+    // `None` outside if `prefix` isn't a path. Doesn't check the padding, so `traverse_ge` can
+    // pass a prefix of its query.
+    fn prefix_end(&self, prefix: &[u8; 8], prefix_len: usize) -> Option<Option<usize>> {
+        if prefix_len == 0 {
+            return Some(None);
+        }
+        let pos = match_path(&Lanes::new(self.nodes, self.edges), prefix, prefix_len)?.trailing_zeros();
+        // An empty mask means the path stopped matching on its last byte.
+        if pos == <VectorOf<LANES> as Vector>::Bitmask::BITS {
+            return None;
+        }
+        Some(Some(pos as usize))
+    }
+
+    // Number of nodes, which is the number of edges the trie was built from.
+    pub fn len(&self) -> usize {
+        let sentinel = VectorOf::<LANES>::splat(SENTINEL_NODE);
+        self.nodes.bytes().simd_ne(sentinel).bitmask().count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Number of nodes with a value, which is one more than the highest value rank.
+    pub fn num_values(&self) -> u32 {
+        let values = self.nodes.bytes() & VectorOf::<LANES>::splat(VALUE_BIT);
+        values.simd_ne(VectorOf::<LANES>::splat(0)).bitmask().count_ones()
+    }
+
+    // Number of nodes with a branch, which is one more than the highest branch rank.
+    pub fn num_branches(&self) -> u32 {
+        let branches = self.nodes.bytes() & VectorOf::<LANES>::splat(BRANCH_BIT);
+        branches.simd_ne(VectorOf::<LANES>::splat(0)).bitmask().count_ones()
+    }
+
+    // Number of edges on the longest path from a root, or 0 for an empty trie.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth as usize
+    }
+
+    // All of the counts above at once, along with how many distinct labels the edges use, e.g. for
+    // watching how close a population of tries gets to filling their lanes.
+    pub fn stats(&self) -> TrieStats {
+        let (edges, nodes) = self.words();
+        let labels: BTreeSet<u8> = (0..LANES).filter(|&i| nodes[i] != SENTINEL_WORD).map(|i| edges[i]).collect();
+        TrieStats {
+            len: self.len(),
+            max_depth: self.max_depth(),
+            num_values: self.num_values(),
+            num_branches: self.num_branches(),
+            num_labels: labels.len(),
+        }
+    }
+
+    // The label on the edge into the node in lane `dfs_ix`, or `None` if the lane is unused or
+    // past the end.
+    pub fn node_label(&self, dfs_ix: u8) -> Option<u8> {
+        let (edges, nodes) = self.words();
+        match nodes.get(dfs_ix as usize) {
+            Some(&node) if node != SENTINEL_WORD => Some(edges[dfs_ix as usize]),
+            _ => None,
+        }
+    }
+
+    // The lane of the parent of the node in lane `dfs_ix`, or `None` if it's a root, the lane is
+    // unused, or it's past the end.
+    pub fn node_parent(&self, dfs_ix: u8) -> Option<u8> {
+        let (_, nodes) = self.words();
+        match nodes.get(dfs_ix as usize) {
+            Some(&node) if !has_flag(node, ROOT_BIT) => Some(word_parent(node) as u8),
+            _ => None,
+        }
+    }
+
+    // Whether the node in lane `dfs_ix` hangs straight off the root. Unused lanes have the root
+    // bit set too, but aren't nodes, so they and lanes past the end are `false`.
+    pub fn is_root_child(&self, dfs_ix: u8) -> bool {
+        let (_, nodes) = self.words();
+        matches!(nodes.get(dfs_ix as usize), Some(&node) if node != SENTINEL_WORD && has_flag(node, ROOT_BIT))
+    }
+
+    // Which bytes label an edge out of the root, i.e. which can start a key, for scanning a buffer
+    // with something cheaper than `traverse` and only looking up at positions that can match.
+    pub fn first_byte_mask(&self) -> [bool; 256] {
+        let (edges, _) = self.packed();
+        let mut mask = [false; 256];
+        for lane in (0..LANES as u8).filter(|&lane| self.is_root_child(lane)) {
+            mask[edges[lane as usize] as usize] = true;
+        }
+        mask
+    }
+
+    // Inverse of `packed`. Like `from_bytes`, nothing checks that the lanes describe a trie, and
+    // lookups on corrupt lanes may return garbage or panic. Run `validate` on the result, or go
+    // through `from_bytes_checked`, for lanes that didn't come out of `packed`.
+    pub fn from_packed(edges: [u8; LANES], nodes: [NodeOf<LANES>; LANES]) -> Self {
+        Self::from_slices(&edges, &nodes.map(NodeWord::widen))
+    }
+
+    // Checks that the lanes describe a trie, as `from_bytes_checked` does, for tries that came from
+    // `from_packed` or `from_bytes`. Lanes are edge numbers rather than DFS order, so a parent
+    // can be in a later lane than its child.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let (edges, nodes) = self.words();
+        check_packed(&edges, &nodes)
+    }
+
+    // Copies the lanes back out as `(edges, nodes)`: a byte per edge, and a node byte per node up
+    // to 32 lanes or a `u16` for 64, laid out like `NodeWord`.
+    pub fn packed(&self) -> ([u8; LANES], [NodeOf<LANES>; LANES]) {
+        let (edges, nodes) = self.words();
+        (edges, nodes.map(NodeWord::narrow))
+    }
+
+    // `packed` with the nodes widened, which is how everything past the vectors reads them.
+    fn words(&self) -> ([u8; LANES], [u16; LANES]) {
+        let mut edges = [0u8; LANES];
+        let mut nodes = [0u16; LANES];
+        self.edges.write_to_slice(&mut edges);
+        self.nodes.write_words(&mut nodes);
+        (edges, nodes)
+    }
+
+    fn edge_vec(&self) -> Vec<Edge> {
+        let (edges, nodes) = self.words();
+        let mut out = vec![];
+        for (number, (&node, &label)) in nodes.iter().zip(&edges).enumerate() {
+            if node == SENTINEL_WORD {
+                continue;
+            }
+            out.push(Edge {
+                parent: if has_flag(node, ROOT_BIT) { None } else { Some(word_parent(node)) },
+                label,
+                number,
+                has_value: has_flag(node, VALUE_BIT),
+                has_branch: has_flag(node, BRANCH_BIT),
+            });
+        }
+        out
+    }
+
+    fn from_slices(edges: &[u8], nodes: &[u16]) -> Self {
+        let edges = VectorOf::<LANES>::from_slice(edges);
+        let max_depth = packed_max_depth(nodes);
+        let nodes = NodesOf::<LANES>::from_words(nodes);
+        Self { edges, nodes, max_depth }
+    }
+
+    // The nodes as `to_bytes` lays them out: each one's bytes, low byte first.
+    fn write_node_bytes(&self, bytes: &mut [u8]) {
+        let (_, nodes) = self.words();
+        let width = core::mem::size_of::<NodeOf<LANES>>();
+        for (chunk, node) in bytes.chunks_exact_mut(width).zip(&nodes) {
+            chunk.copy_from_slice(&node.to_le_bytes()[..width]);
+        }
+    }
+
+    // Inverse of `write_node_bytes`.
+    fn words_from_bytes(bytes: &[u8]) -> [u16; LANES] {
+        let width = core::mem::size_of::<NodeOf<LANES>>();
+        let node = |i: usize| &bytes[i * width..(i + 1) * width];
+        core::array::from_fn(|i| node(i).iter().rev().fold(0, |word, &b| word << 8 | u16::from(b)))
+    }
+
+    // Same as `traverse`, but for paths up to 16 edges deep. Costs two shuffles per level instead
+    // of one, so prefer `traverse` when the query fits in 8 bytes.
+    pub fn traverse_deep(&self, query: &[u8; 16], query_len: usize) -> Lookup {
+        debug_assert_padded(query, query_len);
+        if query_len > self.max_depth as usize {
+            return Lookup::NoSuchPath;
+        }
+        traverse_deep(&Lanes::new(self.nodes, self.edges), query, query_len)
+    }
+
+    // Convenience wrapper around `traverse` and `traverse_deep` that does the padding. Paths are at
+    // most 16 edges long, so longer queries can't match anything.
+    pub fn traverse_slice(&self, query: &[u8]) -> Lookup {
+        if query.len() <= 8 {
+            let mut padded = [0u8; 8];
+            padded[..query.len()].copy_from_slice(query);
+            return self.traverse(&padded, query.len());
+        }
+        if query.len() <= 16 {
+            let mut padded = [0u8; 16];
+            padded[..query.len()].copy_from_slice(query);
+            return self.traverse_deep(&padded, query.len());
+        }
+        Lookup::NoSuchPath
+    }
+
+    // `traverse_slice` over the bytes `query` yields, for queries that aren't in memory all at
+    // once. Pulls at most one byte past the longest path, since by then the query can't match.
+    pub fn traverse_iter<I: IntoIterator<Item = u8>>(&self, query: I) -> Lookup {
+        let mut buf = [0u8; MAX_DEPTH];
+        let mut query_len = 0;
+        for byte in query.into_iter().take(self.max_depth as usize + 1) {
+            if query_len == self.max_depth as usize {
+                return Lookup::NoSuchPath;
+            }
+            buf[query_len] = byte;
+            query_len += 1;
+        }
+        self.traverse_slice(&buf[..query_len])
+    }
+
+    // `traverse_slice` over the UTF-8 bytes of `query`. Keys from `from_strs` are whole strings
+    // too, so a match never ends partway through a character.
+    pub fn traverse_str(&self, query: &str) -> Lookup {
+        self.traverse_slice(query.as_bytes())
+    }
+}
+
+// Reverses a query and pads it for `traverse`, for tries built over reversed keys (e.g. to look up
+// suffixes). The padding has to go after the reversed bytes, not before, so this does both in the
+// right order. Queries longer than 8 keep their length so `traverse` still doesn't match them.
+pub fn reverse_query(query: &[u8]) -> ([u8; 8], usize) {
+    let mut reversed = [0u8; 8];
+    for (slot, &byte) in reversed.iter_mut().zip(query.iter().rev()) {
+        *slot = byte;
+    }
+    (reversed, query.len())
+}
+
+// Prints the tree one node per line, indented by depth, skipping unused lanes:
+//
+//     ByteTrie {
+//         1 (lane 0)
+//             3 (lane 2, parent 0) value
+//     }
+impl<const LANES: usize> fmt::Debug for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (edges, nodes) = self.words();
+        let is_root = |i: usize| has_flag(nodes[i], ROOT_BIT);
+        let parent = |i: usize| word_parent(nodes[i]);
+        let children = |p: Option<usize>| {
+            (0..LANES)
+                .rev()
+                .filter(move |&i| nodes[i] != SENTINEL_WORD)
+                .filter(move |&i| if is_root(i) { p.is_none() } else { Some(parent(i)) == p })
         };
-        let mask = state & u8x16::splat(1 << (query_len as u8 - 1));
-        let match_mask = mask.ne(zero).bitmask();
 
-        let values = (self.nodes & u8x16::splat(1 << 6)).ne(zero).bitmask();
-        let branches = (self.nodes & u8x16::splat(1 << 5)).ne(zero).bitmask();
+        writeln!(f, "ByteTrie {{")?;
+        let mut stack: Vec<(usize, usize)> = children(None).map(|i| (i, 1)).collect();
+        while let Some((i, depth)) = stack.pop() {
+            write!(f, "{:indent$}{} (lane {}", "", edges[i], i, indent = 4 * depth)?;
+            if !is_root(i) {
+                write!(f, ", parent {}", parent(i))?;
+            }
+            write!(f, ")")?;
+            if has_flag(nodes[i], VALUE_BIT) {
+                write!(f, " value")?;
+            }
+            if has_flag(nodes[i], BRANCH_BIT) {
+                write!(f, " branch")?;
+            }
+            writeln!(f)?;
+            // Lanes that only came from `from_bytes` unchecked can be deeper than this.
+            if depth < MAX_DEPTH {
+                stack.extend(children(Some(i)).map(|c| (c, depth + 1)));
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+// Inverse of `FromIterator`: yields the edges in number order, same as `iter_edges`.
+impl<const LANES: usize> IntoIterator for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    type Item = Edge;
+    type IntoIter = vec::IntoIter<Edge>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.edge_vec().into_iter()
+    }
+}
+
+impl<const LANES: usize> Default for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+// Hashes the same bytes `to_bytes` returns, so it agrees with `Eq` comparing lanes.
+impl<const LANES: usize> Hash for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let (edges, nodes) = self.packed();
+        edges.hash(state);
+        nodes.hash(state);
+    }
+}
+
+// Collects the edges into a set and builds the trie with `new`, so like `new` it panics on edges
+// `try_new` rejects, including more edges than the trie has lanes.
+impl<const LANES: usize> FromIterator<Edge> for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn from_iter<I: IntoIterator<Item = Edge>>(edges: I) -> Self {
+        Self::new(&edges.into_iter().collect())
+    }
+}
+
+// `to_bytes` and friends need the byte count in the signature, which can't be computed from
+// `LANES` yet, so spell out each lane count.
+macro_rules! impl_bytes {
+    ($lanes:literal, $bytes:literal) => {
+        impl ByteTrie<$lanes> {
+            // The edge lanes followed by the node lanes, low byte first for 64 lanes' `u16`s.
+            pub fn to_bytes(&self) -> [u8; $bytes] {
+                let (edges, _) = self.packed();
+                let mut bytes = [0u8; $bytes];
+                bytes[..$lanes].copy_from_slice(&edges);
+                self.write_node_bytes(&mut bytes[$lanes..]);
+                bytes
+            }
+
+            // Inverse of `to_bytes`. Nothing checks that the bytes describe a trie, so lookups on
+            // corrupt input may return garbage or panic; use `from_bytes_checked` for untrusted data.
+            pub fn from_bytes(bytes: [u8; $bytes]) -> Self {
+                Self::from_slices(&bytes[..$lanes], &Self::words_from_bytes(&bytes[$lanes..]))
+            }
+
+            pub fn from_bytes_checked(bytes: [u8; $bytes]) -> Result<Self, ValidationError> {
+                check_packed(&bytes[..$lanes], &Self::words_from_bytes(&bytes[$lanes..]))?;
+                Ok(Self::from_bytes(bytes))
+            }
+        }
+    };
+}
+
+impl_bytes!(16, 32);
+impl_bytes!(32, 64);
+impl_bytes!(64, 192);
+
+// `from_packed_const` for the lane counts with a node byte per lane.
+macro_rules! impl_packed_const {
+    ($lanes:literal) => {
+        impl ByteTrie<$lanes> {
+            // Same as `from_packed`, but usable in a `const` or `static`, so a dictionary that's
+            // known at compile time costs nothing to set up. Nothing checks the lanes here either,
+            // so corrupt ones can make lookups return garbage or panic; `validate` catches them.
+            pub const fn from_packed_const(edges: [u8; $lanes], nodes: [u8; $lanes]) -> Self {
+                let mut words = [0u16; $lanes];
+                let mut lane = 0;
+                while lane < $lanes {
+                    words[lane] = nodes[lane] as u16;
+                    lane += 1;
+                }
+                // SAFETY: every backend's vector is `$lanes` bytes in lane order, with no padding
+                // and no invalid bit patterns: the packed_simd and `core::simd` ones are SIMD
+                // types over `[u8; $lanes]`, and the others are `repr(transparent)` wrappers
+                // around one, a byte array, or a pair of 16 lane vectors.
+                unsafe {
+                    Self {
+                        edges: core::mem::transmute::<[u8; $lanes], VectorOf<$lanes>>(edges),
+                        nodes: NodeBytes(core::mem::transmute::<[u8; $lanes], VectorOf<$lanes>>(nodes)),
+                        max_depth: packed_max_depth(&words),
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_packed_const!(16);
+impl_packed_const!(32);
+
+impl ByteTrie<64> {
+    // Same as `from_packed_const` for the narrower tries, splitting the node words into the two
+    // vectors they're stored in.
+    pub const fn from_packed_const(edges: [u8; 64], nodes: [u16; 64]) -> Self {
+        let mut bytes = [0u8; 64];
+        let mut high = [0u8; 64];
+        let mut lane = 0;
+        while lane < 64 {
+            bytes[lane] = nodes[lane] as u8;
+            high[lane] = (nodes[lane] >> 8) as u8;
+            lane += 1;
+        }
+        // SAFETY: same as for the narrower tries, with four 16 lane vectors instead of two.
+        unsafe {
+            Self {
+                edges: core::mem::transmute::<[u8; 64], VectorOf<64>>(edges),
+                nodes: SplitNodes {
+                    bytes: core::mem::transmute::<[u8; 64], VectorOf<64>>(bytes),
+                    high: core::mem::transmute::<[u8; 64], VectorOf<64>>(high),
+                },
+                max_depth: packed_max_depth(&nodes),
+            }
+        }
+    }
+}
+
+// Queries longer than the array don't match anything, so there's no padding to check on them.
+fn debug_assert_padded(query: &[u8], query_len: usize) {
+    if let Some(padding) = query.get(query_len..) {
+        debug_assert!(padding.iter().all(|&b| b == 0), "query {:?} isn't zero past length {}", query, query_len);
+    }
+}
+
+// Sets bit `i` of a lane when its label matches `query[i]`, for up to 8 query bytes.
+fn match_bitsets<V: Vector>(edges: V, query: &[u8]) -> V {
+    let zero = V::splat(0);
+    let mut out = zero;
+    for (i, &byte) in query.iter().enumerate() {
+        let label = V::splat(byte);
+        let bitset = V::splat(1 << i);
+        out |= edges.simd_eq(label).select(bitset, zero);
+    }
+    out
+}
+
+// The parts of a trie's lanes that don't depend on the query. Single lookups build this on every
+// call, and batches build it once up front.
+#[derive(Clone, Copy)]
+struct Lanes<V: Vector> {
+    nodes: V,
+    edges: V,
+    // Shuffle indices for each lane's parent.
+    parents: V,
+    // All ones in root lanes and zero elsewhere.
+    roots: V,
+    // All ones in child lanes and zero elsewhere.
+    children: V,
+}
+
+impl<V: Vector> Lanes<V> {
+    fn new<N: NodeLanes<Vector = V>>(nodes: N, edges: V) -> Self {
+        let zero = V::splat(0);
+        let ones = V::splat(0xFF);
+
+        // Since the root flag doesn't zero out a root's lane during the shuffle, `match_levels`
+        // splits the edge matches up front: roots can only match the first query byte, and
+        // everyone else has to extend their parent's match. Unused lanes hold `SENTINEL_NODE`,
+        // which is neither, so they never match. The flags are all in the node bytes, so that's
+        // all this looks at until it needs the whole pointer.
+        let pointers = nodes.pointers();
+        let nodes = nodes.bytes();
+        let is_root = (nodes & V::splat(ROOT_BIT | PARENT_MASK)).simd_eq(V::splat(ROOT_BIT));
+        let is_child = (nodes & V::splat(ROOT_BIT)).simd_eq(zero);
 
-        let value_match = match_mask & values;
-        let branch_match = match_mask & branches;
+        // Only the parent pointer is a valid shuffle index, and `pointers` has already taken the
+        // flag bits off. `SENTINEL_NODE`'s pointer is past the end of a 16 lane vector, so point
+        // everything without a parent at lane 0; those lanes never match anyway.
+        let parents = is_child.select(pointers, zero);
 
-        let branch_pos = branch_match.trailing_zeros();
-        if branch_pos != 16 {
-            let mask = (1u16 << branch_pos) - 1;
-            return Lookup::Branch((branches & mask).count_ones() as u8);
+        Self {
+            nodes,
+            edges,
+            parents,
+            roots: is_root.select(ones, zero),
+            children: is_child.select(ones, zero),
         }
+    }
+}
+
+// `query` can be at most 8 bytes, like in `match_path`.
+fn traverse<V: Vector>(lanes: &Lanes<V>, query: &[u8], query_len: usize) -> Lookup {
+    match match_path(lanes, query, query_len) {
+        Some(match_mask) => classify(lanes.nodes, match_mask),
+        // The empty path ends at the root, which never has a value or branch, and anything longer
+        // than the query has to go through `traverse_deep`.
+        None => Lookup::NoSuchPath,
+    }
+}
 
+// `match_path` for four queries at once, one level at a time for all of them. Nothing stops
+// early, since a miss in one query doesn't say anything about the rest.
+fn traverse4<V: Vector>(lanes: &Lanes<V>, queries: &[[u8; 8]; 4], lens: &[usize; 4]) -> [Lookup; 4] {
+    let edge_matches = queries.map(|query| match_bitsets(lanes.edges, &query));
+    let child_matches = edge_matches.map(|m| m & lanes.children);
+
+    let mut matches = edge_matches.map(|m| m & lanes.roots);
+    let mut ends = [V::splat(0); 4];
+    let max_len = lens.iter().copied().filter(|&len| len <= 8).max().unwrap_or(0);
+    for level in 1..=max_len {
+        if level > 1 {
+            for (m, &child) in matches.iter_mut().zip(&child_matches) {
+                *m = (m.shuffle(lanes.parents) << 1) & child;
+            }
+        }
+        for ((end, &m), &len) in ends.iter_mut().zip(&matches).zip(lens) {
+            if len == level {
+                *end = m;
+            }
+        }
+    }
+    [0, 1, 2, 3].map(|i| match lens[i] {
+        1..=8 => classify(lanes.nodes, level_mask(ends[i], lens[i])),
+        _ => Lookup::NoSuchPath,
+    })
+}
+
+// The lanes the first `query_len` bytes of `query` end at, or `None` if that's empty or more than
+// `query` holds, or once no lane matches. `query` can be at most 8 bytes. This is `match_levels`
+// one level at a time, since most lookups are misses and a miss usually stops matching within a
+// level or two: no need to shuffle the rest.
+fn match_path<V: Vector>(lanes: &Lanes<V>, query: &[u8], query_len: usize) -> Option<V::Bitmask> {
+    if query_len == 0 || query_len > query.len() {
+        return None;
+    }
+    let zero = V::splat(0);
+    if query_len == 1 {
+        // Only roots can match a single byte, so there's no need for the other seven bitsets.
+        return Some(lanes.edges.simd_eq(V::splat(query[0])).bitmask() & lanes.roots.simd_ne(zero).bitmask());
+    }
+    let edge_matches = match_bitsets(lanes.edges, query);
+    let child_matches = edge_matches & lanes.children;
+
+    let mut matches = edge_matches & lanes.roots;
+    for _ in 1..query_len {
+        if matches == zero {
+            return None;
+        }
+        matches = (matches.shuffle(lanes.parents) << 1) & child_matches;
+    }
+    Some(level_mask(matches, query_len))
+}
+
+// Bit `i` of `levels[k]` is set for each lane that matches `query[i - k..=i]` as a path starting at
+// a root, so `levels[k]` holds every prefix of length `k + 1` at once. `edge_matches` has bit `i`
+// set for each lane whose own label matches `query[i]`, as from `match_bitsets`.
+fn match_levels<V: Vector>(lanes: &Lanes<V>, edge_matches: V) -> [V; 8] {
+    let parents = lanes.parents;
+    let child_matches = edge_matches & lanes.children;
+
+    let matches0 = edge_matches & lanes.roots;
+
+    let matches1 = (matches0.shuffle(parents) << 1) & child_matches;
+    let matches2 = (matches1.shuffle(parents) << 1) & child_matches;
+    let matches3 = (matches2.shuffle(parents) << 1) & child_matches;
+    let matches4 = (matches3.shuffle(parents) << 1) & child_matches;
+    let matches5 = (matches4.shuffle(parents) << 1) & child_matches;
+    let matches6 = (matches5.shuffle(parents) << 1) & child_matches;
+    let matches7 = (matches6.shuffle(parents) << 1) & child_matches;
+
+    [matches0, matches1, matches2, matches3, matches4, matches5, matches6, matches7]
+}
+
+// The lanes in `level` that match the first `prefix_len` bytes of the query.
+fn level_mask<V: Vector>(level: V, prefix_len: usize) -> V::Bitmask {
+    let zero = V::splat(0);
+    let mask = level & V::splat(1 << (prefix_len as u8 - 1));
+    mask.simd_ne(zero).bitmask()
+}
+
+fn longest_prefix_match<V: Vector>(lanes: &Lanes<V>, query: &[u8; 8], query_len: usize) -> Option<(u8, usize)> {
+    if query_len > 8 {
+        return None;
+    }
+    let zero = V::splat(0);
+    let values = (lanes.nodes & V::splat(VALUE_BIT)).simd_ne(zero).bitmask();
+    let levels = match_levels(lanes, match_bitsets(lanes.edges, query));
+
+    for prefix_len in (1..=query_len).rev() {
+        let value_match = level_mask(levels[prefix_len - 1], prefix_len) & values;
         let value_pos = value_match.trailing_zeros();
-        if value_pos != 16 {
-            let mask = (1u16 << value_pos) - 1;
-            return Lookup::Value((values & mask).count_ones() as u8);
+        if value_pos != V::Bitmask::BITS {
+            let mask = V::Bitmask::below(value_pos);
+            return Some(((values & mask).count_ones() as u8, prefix_len));
+        }
+    }
+    None
+}
+
+fn traverse_deep<V: Vector>(lanes: &Lanes<V>, query: &[u8; 16], query_len: usize) -> Lookup {
+    if query_len == 0 || query_len > 16 {
+        return Lookup::NoSuchPath;
+    }
+    let lo_matches = match_bitsets(lanes.edges, &query[..8]);
+    let hi_matches = match_bitsets(lanes.edges, &query[8..]);
+    follow_deep(lanes, lo_matches, hi_matches, query_len)
+}
+
+// The rest of `traverse_deep` once the edge matches for query positions 0-7 and 8-15 are known.
+// `query_len` has to be between 1 and 16.
+fn follow_deep<V: Vector>(lanes: &Lanes<V>, lo_matches: V, hi_matches: V, query_len: usize) -> Lookup {
+    let zero = V::splat(0);
+
+    // A single byte of match bits only covers 8 query positions, so track positions 0-7 and 8-15
+    // in separate vectors and carry bit 7 of the low half into bit 0 of the high half as we go
+    // down a level.
+    let parents = lanes.parents;
+    let lo_child_matches = lo_matches & lanes.children;
+    let hi_child_matches = hi_matches & lanes.children;
+
+    let mut lo = lo_matches & lanes.roots;
+    let mut hi = hi_matches & lanes.roots;
+    for _ in 1..query_len {
+        if lo == zero && hi == zero {
+            return Lookup::NoSuchPath;
+        }
+        let lo_parent = lo.shuffle(parents);
+        let hi_parent = hi.shuffle(parents);
+        lo = (lo_parent << 1) & lo_child_matches;
+        hi = ((hi_parent << 1) | (lo_parent >> 7)) & hi_child_matches;
+    }
+
+    let last = query_len - 1;
+    let mask = if last < 8 {
+        lo & V::splat(1 << last)
+    } else {
+        hi & V::splat(1 << (last - 8))
+    };
+    classify(lanes.nodes, mask.simd_ne(zero).bitmask())
+}
+
+// Turns the set of lanes that match the whole query into a `Lookup`.
+fn classify<V: Vector>(nodes: V, match_mask: V::Bitmask) -> Lookup {
+    let zero = V::splat(0);
+    let values = (nodes & V::splat(VALUE_BIT)).simd_ne(zero).bitmask();
+    let branches = (nodes & V::splat(BRANCH_BIT)).simd_ne(zero).bitmask();
+
+    let value_pos = (match_mask & values).trailing_zeros();
+    let branch_pos = (match_mask & branches).trailing_zeros();
+
+    // Ranks count the flagged lanes before the matched one.
+    let rank = |flags: V::Bitmask, pos: u32| (flags & V::Bitmask::below(pos)).count_ones() as u8;
+    match (value_pos != V::Bitmask::BITS, branch_pos != V::Bitmask::BITS) {
+        (true, true) => Lookup::ValueAndBranch(rank(values, value_pos), rank(branches, branch_pos)),
+        (true, false) => Lookup::Value(rank(values, value_pos)),
+        (false, true) => Lookup::Branch(rank(branches, branch_pos)),
+        (false, false) if match_mask.count_ones() > 0 => Lookup::Exists,
+        (false, false) => Lookup::NoSuchPath,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BuildError {
+    // The trie would need this many nodes, which is more than it has lanes for.
+    TooManyNodes(usize),
+    // A path is this many edges long, which is deeper than `traverse_deep` can follow.
+    DepthExceeded(usize),
+    // The empty key ends at the root, which can't hold a value.
+    EmptyKey,
+    // An edge's parent is this number, which is past the last lane or too big for a node's parent
+    // pointer.
+    ParentPointerOverflow(usize),
+    // More than one edge has this number.
+    DuplicateNumber(usize),
+    // Two edges out of the same node have the same label, so a query can't tell them apart.
+    DuplicateSiblingLabel { parent: Option<usize>, label: u8 },
+    // Same as `DuplicateSiblingLabel`, for a `WideTrie`.
+    DuplicateWideSiblingLabel { parent: Option<usize>, label: u16 },
+    // An edge's parent isn't reachable from a root, either because no edge has that number or
+    // because the parents loop back on themselves.
+    OrphanEdge { number: usize, parent: usize },
+    // `from_edges_auto` got an edge whose parent isn't one of the edges before it.
+    ParentAfterChild { number: usize, parent: usize },
+    // This many edges have a value, or this many have a branch, which is more ranks than a
+    // `Lookup` can count. Only a `SlowTrie` gets big enough for this.
+    TooManyRanks(usize),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::TooManyNodes(n) => write!(f, "trie needs {} nodes, which is more than it has lanes for", n),
+            BuildError::DepthExceeded(d) => write!(f, "path is {} edges deep, but at most {} are supported", d, MAX_DEPTH),
+            BuildError::EmptyKey => write!(f, "the empty key can't hold a value"),
+            BuildError::ParentPointerOverflow(p) => {
+                write!(f, "parent {} is past the last lane or doesn't fit in a node's parent pointer", p)
+            },
+            BuildError::DuplicateNumber(n) => write!(f, "more than one edge is numbered {}", n),
+            BuildError::DuplicateSiblingLabel { parent: Some(p), label } => {
+                write!(f, "node {} has more than one edge labeled {}", p, label)
+            },
+            BuildError::DuplicateSiblingLabel { parent: None, label } => {
+                write!(f, "more than one root edge is labeled {}", label)
+            },
+            BuildError::DuplicateWideSiblingLabel { parent: Some(p), label } => {
+                write!(f, "node {} has more than one edge labeled {}", p, label)
+            },
+            BuildError::DuplicateWideSiblingLabel { parent: None, label } => {
+                write!(f, "more than one root edge is labeled {}", label)
+            },
+            BuildError::OrphanEdge { number, parent } => {
+                write!(f, "edge {} hangs off {}, which isn't reachable from a root", number, parent)
+            },
+            BuildError::ParentAfterChild { number, parent } => {
+                write!(f, "edge {} hangs off {}, which doesn't come before it", number, parent)
+            },
+            BuildError::TooManyRanks(n) => write!(f, "{} edges share a flag, but ranks only go up to 255", n),
         }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
+
+// Ways packed lanes can fail to describe a trie. Each variant carries the offending lane.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValidationError {
+    // An unused lane has a nonzero label.
+    UnusedLabel(usize),
+    // A root has a nonzero parent pointer.
+    RootWithParent(usize),
+    // A node's parent pointer is out of range or points at an unused lane.
+    DanglingParent(usize),
+    // A node doesn't reach a root within `MAX_DEPTH` edges, either because it's too deep or
+    // because its parent pointers form a cycle.
+    TooDeep(usize),
+    // A node has the same label as a sibling in an earlier lane, so a query could match both.
+    DuplicateSiblingLabel(usize),
+}
 
-        Lookup::None
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::UnusedLabel(i) => write!(f, "unused lane {} has a label", i),
+            ValidationError::RootWithParent(i) => write!(f, "root {} has a parent pointer", i),
+            ValidationError::DanglingParent(i) => write!(f, "node {} points at an unused lane", i),
+            ValidationError::TooDeep(i) => write!(f, "node {} is more than {} edges from a root", i, MAX_DEPTH),
+            ValidationError::DuplicateSiblingLabel(i) => write!(f, "node {} has the same label as a sibling", i),
+        }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationError {}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum Lookup {
-    None,
+    // The query isn't a path in the trie. The empty query counts as this too, since the root has
+    // no edge for it to end on.
+    NoSuchPath,
+    // The query is a path, but the node it ends at has neither a value nor a branch.
+    Exists,
     Branch(u8),
     Value(u8),
+    // The node has both, so this carries its value rank and then its branch rank.
+    ValueAndBranch(u8, u8),
+}
+
+// Which `Lookup` a lookup was, without its ranks.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LookupKind {
+    NoSuchPath,
+    Exists,
+    Branch,
+    Value,
+    ValueAndBranch,
+}
+
+// Flattens a `Lookup` to its kind and one `u16` of rank, e.g. for code that stores lookups from
+// several trie widths in the same table. A `ByteTrie` rank is below its lane count, so at most 63,
+// and `SlowTrie` stops at 255, so `ValueAndBranch` can put its value rank in the high byte and its
+// branch rank in the low one. The kinds without a rank get 0.
+impl From<Lookup> for (LookupKind, u16) {
+    fn from(lookup: Lookup) -> Self {
+        match lookup {
+            Lookup::NoSuchPath => (LookupKind::NoSuchPath, 0),
+            Lookup::Exists => (LookupKind::Exists, 0),
+            Lookup::Branch(rank) => (LookupKind::Branch, rank.into()),
+            Lookup::Value(rank) => (LookupKind::Value, rank.into()),
+            Lookup::ValueAndBranch(value_rank, branch_rank) => {
+                (LookupKind::ValueAndBranch, u16::from_be_bytes([value_rank, branch_rank]))
+            },
+        }
+    }
+}
+
+// Room for the temporaries `ByteTrie::rebuild_into` needs, kept between builds so they only
+// allocate until they've grown to fit.
+#[derive(Clone, Debug, Default)]
+pub struct BuildScratch {
+    sorted: Vec<Edge>,
+    stack: Vec<(Option<Edge>, usize)>,
+}
+
+impl BuildScratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// What `ByteTrie::stats` counts, with each field matching the method of the same name.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TrieStats {
+    pub len: usize,
+    pub max_depth: usize,
+    pub num_values: u32,
+    pub num_branches: u32,
+    // Distinct labels across all the edges.
+    pub num_labels: usize,
+}
+
+// Which flag `traverse_with_precedence` reports for a node that has both.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Precedence {
+    Value,
+    Branch,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
@@ -113,54 +1597,195 @@ impl Edge {
     }
 }
 
-fn build_tree<const N: usize>(edges: &BTreeSet<Edge>, max_depth: usize) -> ([u8; N], [u8; N]) {
-    let mut packed_edges = [0b0000_0000; N];
-    let mut packed_nodes = [0b0000_0000; N];
+// `edges` has to be sorted, as from iterating a `BTreeSet<Edge>`, so that each node's children are
+// a contiguous run.
+fn build_tree<const N: usize, W: NodeWord>(edges: &[Edge], max_depth: usize) -> Result<([u8; N], [W; N]), BuildError> {
+    build_tree_with(edges, max_depth, &mut vec![])
+}
 
-    let mut next_dfs = 0u8;
-    let mut dfs_assignments: HashMap<usize, u8> = HashMap::new();
-    let mut stack: Vec<(Option<Edge>, usize)> = vec![(None, 0)];
+// `build_tree` with the walk's stack passed in, so repeated builds can reuse its allocation. It's
+// the only one: every number has to be below `N`, so the rest of the bookkeeping fits in arrays,
+// and since `edges` are sorted, siblings with the same label sit next to each other.
+fn build_tree_with<const N: usize, W: NodeWord>(
+    edges: &[Edge],
+    max_depth: usize,
+    stack: &mut Vec<(Option<Edge>, usize)>,
+) -> Result<([u8; N], [W; N]), BuildError> {
+    let mut packed_edges = [0b0000_0000; N];
+    let mut packed_nodes = [W::narrow(SENTINEL_WORD); N];
 
-    let edge_numbers = edges.iter().map(|e| e.number).collect::<BTreeSet<_>>();
-    assert_eq!(edge_numbers.len(), edges.len());
-    assert!(edge_numbers.iter().all(|&e| e < 16));
+    let mut edge_numbers = [false; N];
+    let mut prev: Option<&Edge> = None;
+    for edge in edges {
+        if edge.number >= N {
+            return Err(BuildError::TooManyNodes(edge.number + 1));
+        }
+        if core::mem::replace(&mut edge_numbers[edge.number], true) {
+            return Err(BuildError::DuplicateNumber(edge.number));
+        }
+        if prev.is_some_and(|p| (p.parent, p.label) == (edge.parent, edge.label)) {
+            return Err(BuildError::DuplicateSiblingLabel { parent: edge.parent, label: edge.label });
+        }
+        match edge.parent {
+            // Past the last lane, or too big for `W`'s pointer bits if `N` ever outgrows them.
+            Some(parent) if parent >= N || parent >> W::PARENT_BITS != 0 => {
+                return Err(BuildError::ParentPointerOverflow(parent))
+            },
+            _ => (),
+        }
+        prev = Some(edge);
+    }
 
+    let mut reached = [false; N];
+    stack.clear();
+    stack.push((None, 0));
     while let Some((maybe_edge, depth)) = stack.pop() {
-        assert!(depth <= max_depth);
+        if depth > max_depth {
+            return Err(BuildError::DepthExceeded(depth));
+        }
         if let Some(edge) = maybe_edge {
-            let dfs_number = next_dfs;
-            next_dfs += 1;
-            assert!(dfs_assignments.insert(edge.number, dfs_number).is_none());
-
-            let mut parent_byte = match edge.parent {
-                Some(input_ix) => {
-                    // let dfs_ix = dfs_assignments[&input_ix];
-                    // assert!(dfs_ix < (N as u8));
-                    // dfs_ix
-                    assert!(input_ix < N);
-                    input_ix as u8
-                },
-                None => 0b1000_0000,
+            reached[edge.number] = true;
+
+            // Parents were checked against `W`'s pointer bits above, so they don't spill into the
+            // flags.
+            let mut node = match edge.parent {
+                Some(parent) => parent_word(parent),
+                None => ROOT_BIT.into(),
             };
             if edge.has_value {
-                parent_byte |= 1 << 6;
+                node |= u16::from(VALUE_BIT);
             }
             if edge.has_branch {
-                parent_byte |= 1 << 5;
+                node |= u16::from(BRANCH_BIT);
             }
 
-            // packed_nodes[dfs_number as usize] = parent_byte;
-            // packed_edges[dfs_number as usize] = edge.label;
-            packed_nodes[edge.number as usize] = parent_byte;
-            packed_edges[edge.number as usize] = edge.label;
+            packed_nodes[edge.number] = W::narrow(node);
+            packed_edges[edge.number] = edge.label;
         }
 
         let src_start = maybe_edge.map(|e| e.number);
         let src_end = Some(maybe_edge.map(|e| e.number + 1).unwrap_or(0));
-        for &edge in edges.range(Edge::bound(src_start)..Edge::bound(src_end)).rev() {
+        let start = edges.partition_point(|e| *e < Edge::bound(src_start));
+        let end = edges.partition_point(|e| *e < Edge::bound(src_end));
+        for &edge in edges[start..end].iter().rev() {
             stack.push((Some(edge), depth + 1));
         }
     }
 
-    (packed_edges, packed_nodes)
+    // The walk only follows edges down from the roots, so anything it didn't reach is cut off.
+    if let Some(orphan) = edges.iter().filter(|e| !reached[e.number]).min_by_key(|e| e.number) {
+        return Err(BuildError::OrphanEdge { number: orphan.number, parent: orphan.parent.unwrap() });
+    }
+
+    Ok((packed_edges, packed_nodes))
+}
+
+// Whether `node` hangs off `parent`'s lane, or off the root for `None`. Unused lanes never do.
+fn is_child_of(node: u16, parent: Option<usize>) -> bool {
+    match parent {
+        None => node != SENTINEL_WORD && has_flag(node, ROOT_BIT),
+        Some(parent) => !has_flag(node, ROOT_BIT) && word_parent(node) == parent,
+    }
+}
+
+// What `traverse` returns for a path ending at `lane`: ranks count the flagged lanes before it,
+// matched or not. A lane with no flags, including an unused one, is `Exists`.
+fn lookup_for_lane(nodes: &[u16], lane: usize) -> Lookup {
+    let value_rank = nodes[..lane].iter().filter(|&&n| has_flag(n, VALUE_BIT)).count() as u8;
+    let branch_rank = nodes[..lane].iter().filter(|&&n| has_flag(n, BRANCH_BIT)).count() as u8;
+    match (has_flag(nodes[lane], VALUE_BIT), has_flag(nodes[lane], BRANCH_BIT)) {
+        (true, true) => Lookup::ValueAndBranch(value_rank, branch_rank),
+        (true, false) => Lookup::Value(value_rank),
+        (false, true) => Lookup::Branch(branch_rank),
+        (false, false) => Lookup::Exists,
+    }
+}
+
+// `lane` and then each of its ancestors up to a root. Well-formed tries get there within
+// `MAX_DEPTH` lanes, so the bound only matters for ones that came from `from_bytes` unchecked.
+fn ancestors(nodes: &[u16], lane: usize) -> impl Iterator<Item = usize> + '_ {
+    let mut next = Some(lane);
+    let walk = core::iter::from_fn(move || {
+        let current = next?;
+        if !has_flag(nodes[current], ROOT_BIT) {
+            next = Some(word_parent(nodes[current]) % nodes.len());
+        } else {
+            next = None;
+        }
+        Some(current)
+    });
+    walk.take(MAX_DEPTH)
+}
+
+// The most `ancestors` any used lane has, written out with loops so `from_packed_const` can call
+// it. Like `ancestors`, it stops at `MAX_DEPTH`, so lanes that loop back on themselves count as
+// deep as a path can be rather than hanging.
+const fn packed_max_depth(nodes: &[u16]) -> u8 {
+    let mut max_depth = 0;
+    let mut lane = 0;
+    while lane < nodes.len() {
+        if nodes[lane] != SENTINEL_WORD {
+            let mut depth = 1;
+            let mut current = lane;
+            while depth < MAX_DEPTH && !has_flag(nodes[current], ROOT_BIT) {
+                current = word_parent(nodes[current]) % nodes.len();
+                depth += 1;
+            }
+            if depth > max_depth {
+                max_depth = depth;
+            }
+        }
+        lane += 1;
+    }
+    max_depth as u8
+}
+
+// Checks that packed lanes describe a trie `traverse` can safely run over: unused lanes hold
+// `SENTINEL_NODE` with a zero label, roots have a zero parent pointer, and every other node points
+// at a used lane and reaches a root within `MAX_DEPTH` edges. Lanes follow edge numbers rather
+// than DFS order, so parents may come after their children.
+fn check_packed(edges: &[u8], nodes: &[u16]) -> Result<(), ValidationError> {
+    assert_eq!(edges.len(), nodes.len());
+    let is_used = |i: usize| nodes[i] != SENTINEL_WORD;
+
+    for (i, (&node, &label)) in nodes.iter().zip(edges).enumerate() {
+        if !is_used(i) {
+            if label != 0 {
+                return Err(ValidationError::UnusedLabel(i));
+            }
+            continue;
+        }
+        let parent = word_parent(node);
+        if has_flag(node, ROOT_BIT) {
+            if parent != 0 {
+                return Err(ValidationError::RootWithParent(i));
+            }
+            continue;
+        }
+        if parent >= nodes.len() || !is_used(parent) {
+            return Err(ValidationError::DanglingParent(i));
+        }
+    }
+
+    let mut sibling_labels = BTreeSet::new();
+    for (i, (&node, &label)) in nodes.iter().zip(edges).enumerate().filter(|&(i, _)| is_used(i)) {
+        let parent = if has_flag(node, ROOT_BIT) { None } else { Some(word_parent(node)) };
+        if !sibling_labels.insert((parent, label)) {
+            return Err(ValidationError::DuplicateSiblingLabel(i));
+        }
+    }
+
+    // Now that every parent pointer is known to land on a node, walk up from each one.
+    for i in (0..nodes.len()).filter(|&i| is_used(i)) {
+        let mut depth = 1;
+        let mut current = i;
+        while !has_flag(nodes[current], ROOT_BIT) {
+            current = word_parent(nodes[current]);
+            depth += 1;
+            if depth > MAX_DEPTH {
+                return Err(ValidationError::TooDeep(i));
+            }
+        }
+    }
+    Ok(())
 }
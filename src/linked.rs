@@ -0,0 +1,64 @@
+use crate::{ByteTrie, LaneCount, Lookup, SupportedLaneCount};
+use alloc::vec::Vec;
+
+// A `ByteTrie` whose branches lead to more tries, with `Lookup::Branch(r)` continuing in
+// `branches[r]`, so keys can run as long as the chain of tries does.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkedTrie<const LANES: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    trie: ByteTrie<LANES>,
+    branches: Vec<LinkedTrie<LANES>>,
+}
+
+pub type LinkedTrie16 = LinkedTrie<16>;
+pub type LinkedTrie32 = LinkedTrie<32>;
+pub type LinkedTrie64 = LinkedTrie<64>;
+
+impl<const LANES: usize> LinkedTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // `branches` must hold one trie per branch, in branch rank order.
+    pub fn new(trie: ByteTrie<LANES>, branches: Vec<LinkedTrie<LANES>>) -> Self {
+        assert_eq!(branches.len(), trie.num_branches() as usize);
+        Self { trie, branches }
+    }
+
+    // A trie with no branches to follow, for the ends of the chain.
+    pub fn leaf(trie: ByteTrie<LANES>) -> Self {
+        Self::new(trie, Vec::new())
+    }
+
+    // Follows `key` through the chain, returning the trie it ends in along with the value or branch
+    // `traverse_slice` finds there, or `None` if there's nothing at the end. In each trie, the rest
+    // of the key is looked up whole if something's there, and otherwise continues in the branch at
+    // the end of its longest prefix that has one. There's no backtracking to shorter prefixes if
+    // that branch doesn't have the rest, so the key misses.
+    pub fn traverse(&self, key: &[u8]) -> Option<(&Self, Lookup)> {
+        let mut current = self;
+        let mut rest = key;
+        loop {
+            match current.trie.traverse_slice(rest) {
+                Lookup::NoSuchPath | Lookup::Exists => (),
+                lookup => return Some((current, lookup)),
+            }
+            let longest = rest.len().min(current.trie.max_depth() + 1);
+            let (len, rank) = (1..longest).rev().find_map(|len| match current.trie.traverse_slice(&rest[..len]) {
+                Lookup::Branch(rank) | Lookup::ValueAndBranch(_, rank) => Some((len, rank)),
+                _ => None,
+            })?;
+            current = &current.branches[rank as usize];
+            rest = &rest[len..];
+        }
+    }
+
+    pub fn trie(&self) -> &ByteTrie<LANES> {
+        &self.trie
+    }
+
+    pub fn branches(&self) -> &[LinkedTrie<LANES>] {
+        &self.branches
+    }
+}
@@ -0,0 +1,77 @@
+use crate::Edge;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+// Builds a `BTreeSet<Edge>` from nested labels, numbering edges in preorder and filling in their
+// parents, e.g.
+//
+//     trie! { 1 => { 3 = value, 4 = branch }, 2 = value + branch => { 5 = value } }
+//
+// Each node is a label, then optionally `=` and its flags (`value`, `branch` or `value + branch`),
+// then optionally `=>` and its children in braces. Labels are single tokens, so wrap anything
+// longer than a literal or a name in parentheses. A misspelled flag doesn't compile; a repeated
+// sibling label does, but `ByteTrie::try_new` turns it away like any other duplicate.
+#[macro_export]
+macro_rules! trie {
+    () => {
+        $crate::EdgeList::new().into_set()
+    };
+    ($($nodes:tt)+) => {{
+        let mut edges = $crate::EdgeList::new();
+        $crate::__trie_nodes!(edges, None; $($nodes)*);
+        edges.into_set()
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trie_nodes {
+    ($edges:ident, $parent:expr;) => {};
+    (
+        $edges:ident, $parent:expr;
+        $label:tt $(= $flag:ident $(+ $more:ident)*)? $(=> { $($children:tt)* })? $(, $($rest:tt)*)?
+    ) => {
+        {
+            let flags: &[(bool, bool)] = &[$($crate::__trie_flag!($flag) $(, $crate::__trie_flag!($more))*)?];
+            let has_value = flags.iter().any(|flag| flag.0);
+            let has_branch = flags.iter().any(|flag| flag.1);
+            let _number = $edges.push($parent, $label, has_value, has_branch);
+            $($crate::__trie_nodes!($edges, Some(_number); $($children)*);)?
+        }
+        $($crate::__trie_nodes!($edges, $parent; $($rest)*);)?
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trie_flag {
+    (value) => {
+        (true, false)
+    };
+    (branch) => {
+        (false, true)
+    };
+}
+
+// Where `trie!` collects its edges. Only public so the macro can name it from other crates.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct EdgeList {
+    edges: Vec<Edge>,
+}
+
+impl EdgeList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, parent: Option<usize>, label: u8, has_value: bool, has_branch: bool) -> usize {
+        let number = self.edges.len();
+        self.edges.push(Edge { parent, label, number, has_value, has_branch });
+        number
+    }
+
+    pub fn into_set(self) -> BTreeSet<Edge> {
+        self.edges.into_iter().collect()
+    }
+}
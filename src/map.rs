@@ -0,0 +1,67 @@
+use crate::{BuildError, ByteTrie, Edge, LaneCount, Lookup, SupportedLaneCount};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+// A `ByteTrie` that owns its values instead of handing out value ranks. The trie itself stays a
+// pair of vectors, and the values live next to it ordered by rank.
+pub struct ByteMap<const LANES: usize, V>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    trie: ByteTrie<LANES>,
+    values: Box<[V]>,
+}
+
+pub type ByteMap16<V> = ByteMap<16, V>;
+pub type ByteMap32<V> = ByteMap<32, V>;
+pub type ByteMap64<V> = ByteMap<64, V>;
+
+impl<const LANES: usize, V> ByteMap<LANES, V>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // `values` must yield one value per `has_value` edge, in value rank order (i.e. ordered by
+    // edge number).
+    pub fn new(edges: &BTreeSet<Edge>, values: impl IntoIterator<Item = V>) -> Self {
+        let trie = ByteTrie::new(edges);
+        let values: Box<[V]> = values.into_iter().collect();
+        assert_eq!(values.len(), edges.iter().filter(|e| e.has_value).count());
+        Self { trie, values }
+    }
+
+    // Builds the trie with `ByteTrie::from_keys` and lines the values up with the ranks it hands
+    // out, so any map from keys to values works, e.g. a `HashMap` or `BTreeMap`. When a key comes
+    // up more than once, the last value wins.
+    pub fn from_map<M: IntoIterator<Item = (Vec<u8>, V)>>(map: M) -> Result<Self, BuildError> {
+        let sorted: BTreeMap<Vec<u8>, V> = map.into_iter().collect();
+        let trie = ByteTrie::from_keys(sorted.keys().cloned())?;
+        Ok(Self { trie, values: sorted.into_values().collect() })
+    }
+
+    pub fn get(&self, query: &[u8; 8], query_len: usize) -> Option<&V> {
+        self.trie.value_rank(query, query_len).map(|rank| &self.values[rank as usize])
+    }
+
+    pub fn traverse(&self, query: &[u8; 8], query_len: usize) -> Lookup {
+        self.trie.traverse(query, query_len)
+    }
+
+    // Every key in the map along with its value, in value rank order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, &V)> {
+        self.trie.value_entries().into_iter().map(move |(rank, path)| (path, &self.values[rank as usize]))
+    }
+
+    // Takes the values back out in value rank order, the same order as `values`.
+    pub fn into_values(self) -> impl Iterator<Item = V> {
+        self.values.into_vec().into_iter()
+    }
+
+    pub fn trie(&self) -> &ByteTrie<LANES> {
+        &self.trie
+    }
+
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+}
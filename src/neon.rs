@@ -0,0 +1,241 @@
+use crate::simd::{Mask, Vector};
+use core::arch::aarch64::*;
+use core::ops::{BitAnd, BitOr, BitOrAssign, Shl, Shr};
+
+// 16 lanes in a `uint8x16_t`, and 32 and 64 lanes in two and four of them. The packed_simd calls
+// `traverse` was written against map onto NEON like so:
+//
+// * `shuffle1_dyn` -> `vqtbl1q_u8` for 16 lanes, and `vqtbl2q_u8` and `vqtbl4q_u8` on each part
+//   for 32 and 64, which look up a 16 byte output in a 32 or 64 byte table. All of them zero lanes
+//   whose index is past the end of the table, but `Lanes::new` only produces in-range indices.
+// * `eq`/`ne` -> `vceqq_u8` (and `vmvnq_u8` of it), which produce all-ones lanes, and `select` ->
+//   `vbslq_u8` over those.
+// * `bitmask` has no NEON instruction. Keep bit `i % 8` of each lane, then add up each half of
+//   eight lanes with `vaddv_u8`, since the bits don't overlap.
+//
+// NEON is part of the aarch64 baseline, so the intrinsics are only `unsafe` for being
+// `#[target_feature]` functions and the `unsafe` blocks around them don't need any more SAFETY
+// comments than that. The loads and stores still do.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct Neon16(uint8x16_t);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Neon16Mask(uint8x16_t);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Neon32([Neon16; 2]);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Neon32Mask([Neon16Mask; 2]);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Neon64([Neon16; 4]);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Neon64Mask([Neon16Mask; 4]);
+
+const LANE_BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+impl PartialEq for Neon16 {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { vminvq_u8(vceqq_u8(self.0, other.0)) == 0xFF }
+    }
+}
+
+impl Eq for Neon16 {}
+
+impl BitAnd for Neon16 {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Neon16(unsafe { vandq_u8(self.0, other.0) })
+    }
+}
+
+impl BitOr for Neon16 {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Neon16(unsafe { vorrq_u8(self.0, other.0) })
+    }
+}
+
+impl BitOrAssign for Neon16 {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = *self | other;
+    }
+}
+
+// `vshlq_u8` shifts right for negative amounts.
+impl Shl<u32> for Neon16 {
+    type Output = Self;
+
+    fn shl(self, amount: u32) -> Self {
+        Neon16(unsafe { vshlq_u8(self.0, vdupq_n_s8(amount as i8)) })
+    }
+}
+
+impl Shr<u32> for Neon16 {
+    type Output = Self;
+
+    fn shr(self, amount: u32) -> Self {
+        Neon16(unsafe { vshlq_u8(self.0, vdupq_n_s8(-(amount as i8))) })
+    }
+}
+
+impl Vector for Neon16 {
+    type Mask = Neon16Mask;
+    type Bitmask = u16;
+
+    fn splat(byte: u8) -> Self {
+        Neon16(unsafe { vdupq_n_u8(byte) })
+    }
+
+    fn from_slice(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..16];
+        // SAFETY: `bytes` is 16 bytes long, and `vld1q_u8` doesn't need them aligned.
+        Neon16(unsafe { vld1q_u8(bytes.as_ptr()) })
+    }
+
+    fn write_to_slice(self, bytes: &mut [u8]) {
+        let bytes = &mut bytes[..16];
+        // SAFETY: `bytes` is 16 bytes long, and `vst1q_u8` doesn't need them aligned.
+        unsafe { vst1q_u8(bytes.as_mut_ptr(), self.0) }
+    }
+
+    fn simd_eq(self, other: Self) -> Neon16Mask {
+        Neon16Mask(unsafe { vceqq_u8(self.0, other.0) })
+    }
+
+    fn simd_ne(self, other: Self) -> Neon16Mask {
+        Neon16Mask(unsafe { vmvnq_u8(vceqq_u8(self.0, other.0)) })
+    }
+
+    fn shuffle(self, indices: Self) -> Self {
+        Neon16(unsafe { vqtbl1q_u8(self.0, indices.0) })
+    }
+}
+
+impl Mask for Neon16Mask {
+    type Vector = Neon16;
+    type Bitmask = u16;
+
+    fn select(self, a: Neon16, b: Neon16) -> Neon16 {
+        Neon16(unsafe { vbslq_u8(self.0, a.0, b.0) })
+    }
+
+    fn bitmask(self) -> u16 {
+        let bits = Neon16(self.0) & Neon16::from_slice(&LANE_BITS);
+        let (low, high) = unsafe { (vaddv_u8(vget_low_u8(bits.0)), vaddv_u8(vget_high_u8(bits.0))) };
+        low as u16 | (high as u16) << 8
+    }
+}
+
+// 32 and 64 lanes are two and four 16 lane vectors side by side, which the table lookups take
+// whole.
+macro_rules! impl_wide {
+    ($vector:ident, $mask:ident, $parts:literal, $bitmask:ident, $table:ident($($part:literal),*), $lookup:ident) => {
+        impl $vector {
+            fn map(self, f: impl Fn(Neon16) -> Neon16) -> Self {
+                $vector(self.0.map(f))
+            }
+
+            fn zip(self, other: Self, f: impl Fn(Neon16, Neon16) -> Neon16) -> Self {
+                $vector(core::array::from_fn(|i| f(self.0[i], other.0[i])))
+            }
+        }
+
+        impl BitAnd for $vector {
+            type Output = Self;
+
+            fn bitand(self, other: Self) -> Self {
+                self.zip(other, |a, b| a & b)
+            }
+        }
+
+        impl BitOr for $vector {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                self.zip(other, |a, b| a | b)
+            }
+        }
+
+        impl BitOrAssign for $vector {
+            fn bitor_assign(&mut self, other: Self) {
+                *self = *self | other;
+            }
+        }
+
+        impl Shl<u32> for $vector {
+            type Output = Self;
+
+            fn shl(self, amount: u32) -> Self {
+                self.map(|a| a << amount)
+            }
+        }
+
+        impl Shr<u32> for $vector {
+            type Output = Self;
+
+            fn shr(self, amount: u32) -> Self {
+                self.map(|a| a >> amount)
+            }
+        }
+
+        impl Vector for $vector {
+            type Mask = $mask;
+            type Bitmask = $bitmask;
+
+            fn splat(byte: u8) -> Self {
+                $vector([Neon16::splat(byte); $parts])
+            }
+
+            fn from_slice(bytes: &[u8]) -> Self {
+                $vector(core::array::from_fn(|i| Neon16::from_slice(&bytes[16 * i..16 * (i + 1)])))
+            }
+
+            fn write_to_slice(self, bytes: &mut [u8]) {
+                for (i, part) in self.0.iter().enumerate() {
+                    part.write_to_slice(&mut bytes[16 * i..16 * (i + 1)]);
+                }
+            }
+
+            fn simd_eq(self, other: Self) -> $mask {
+                $mask(core::array::from_fn(|i| self.0[i].simd_eq(other.0[i])))
+            }
+
+            fn simd_ne(self, other: Self) -> $mask {
+                $mask(core::array::from_fn(|i| self.0[i].simd_ne(other.0[i])))
+            }
+
+            fn shuffle(self, indices: Self) -> Self {
+                let table = $table($(self.0[$part].0),*);
+                indices.map(|part| Neon16(unsafe { $lookup(table, part.0) }))
+            }
+        }
+
+        impl Mask for $mask {
+            type Vector = $vector;
+            type Bitmask = $bitmask;
+
+            fn select(self, a: $vector, b: $vector) -> $vector {
+                $vector(core::array::from_fn(|i| self.0[i].select(a.0[i], b.0[i])))
+            }
+
+            fn bitmask(self) -> $bitmask {
+                let mut out = 0;
+                for (i, part) in self.0.iter().enumerate() {
+                    out |= (part.bitmask() as $bitmask) << (16 * i);
+                }
+                out
+            }
+        }
+    };
+}
+
+impl_wide!(Neon32, Neon32Mask, 2, u32, uint8x16x2_t(0, 1), vqtbl2q_u8);
+impl_wide!(Neon64, Neon64Mask, 4, u64, uint8x16x4_t(0, 1, 2, 3), vqtbl4q_u8);
@@ -0,0 +1,23 @@
+use crate::{ByteTrie, LaneCount, Lookup, SupportedLaneCount};
+use alloc::vec::Vec;
+use rayon::prelude::*;
+
+// Queries per rayon task. Each task runs `traverse_batch` over its chunk, so this is also how
+// often the per-trie setup gets redone.
+const CHUNK_SIZE: usize = 4096;
+
+impl<const LANES: usize> ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // `traverse` for every query, spread across rayon's thread pool. The results are in the same
+    // order as the queries.
+    pub fn par_traverse(&self, queries: &[([u8; 8], usize)]) -> Vec<Lookup> {
+        let mut out: Vec<Lookup> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+        queries
+            .par_chunks(CHUNK_SIZE)
+            .zip(out.par_chunks_mut(CHUNK_SIZE))
+            .for_each(|(queries, out)| self.traverse_batch(queries, out));
+        out
+    }
+}
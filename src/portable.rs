@@ -0,0 +1,113 @@
+use crate::simd::{Mask, Vector};
+use core::simd::cmp::SimdPartialEq;
+use core::simd::{self, Select, Simd};
+use core::ops::{BitAnd, BitOr, BitOrAssign, Shl, Shr};
+
+// `Simd` only shifts by its own element type, and the trait bounds on `Vector` want a `u32`
+// shift, so wrap it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Portable<const N: usize>(Simd<u8, N>);
+
+#[derive(Clone, Copy, Debug)]
+pub struct PortableMask<const N: usize>(simd::Mask<i8, N>);
+
+// `core::simd` versions of the packed_simd calls `traverse` was written against:
+//
+// * `shuffle1_dyn` -> `swizzle_dyn`. packed_simd lowers the 16 lane shuffle to `pshufb`
+//   (`_mm_shuffle_epi8`) with ssse3, and `swizzle_dyn` does the same. The two only disagree on
+//   out of range indices: `pshufb` looks at the high bit and the low four bits, while
+//   `swizzle_dyn` zeroes any index past the last lane. The parents `Lanes::new` builds never
+//   include one, so they're interchangeable here. The 32 lane shuffle is two `pshufb`s plus a
+//   blend with avx2 in both crates. The 64 lane one is only a single instruction
+//   (`vpermb`) with avx512vbmi.
+// * `eq`/`ne` -> `simd_eq`/`simd_ne`, and `select` -> `Select::select`, which both compile to the
+//   same compare and blend.
+// * `bitmask` -> `to_bitmask`, which is `pmovmskb` but always returns a `u64`.
+macro_rules! impl_vector {
+    ($lanes:literal, $bitmask:ident) => {
+        impl BitAnd for Portable<$lanes> {
+            type Output = Self;
+
+            fn bitand(self, other: Self) -> Self {
+                Portable(self.0 & other.0)
+            }
+        }
+
+        impl BitOr for Portable<$lanes> {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                Portable(self.0 | other.0)
+            }
+        }
+
+        impl BitOrAssign for Portable<$lanes> {
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl Shl<u32> for Portable<$lanes> {
+            type Output = Self;
+
+            fn shl(self, amount: u32) -> Self {
+                Portable(self.0 << amount as u8)
+            }
+        }
+
+        impl Shr<u32> for Portable<$lanes> {
+            type Output = Self;
+
+            fn shr(self, amount: u32) -> Self {
+                Portable(self.0 >> amount as u8)
+            }
+        }
+
+        impl Vector for Portable<$lanes> {
+            type Mask = PortableMask<$lanes>;
+            type Bitmask = $bitmask;
+
+            fn splat(byte: u8) -> Self {
+                Portable(Simd::splat(byte))
+            }
+
+            fn from_slice(bytes: &[u8]) -> Self {
+                Portable(Simd::from_slice(bytes))
+            }
+
+            fn write_to_slice(self, bytes: &mut [u8]) {
+                self.0.copy_to_slice(&mut bytes[..$lanes])
+            }
+
+            fn simd_eq(self, other: Self) -> PortableMask<$lanes> {
+                PortableMask(self.0.simd_eq(other.0))
+            }
+
+            fn simd_ne(self, other: Self) -> PortableMask<$lanes> {
+                PortableMask(self.0.simd_ne(other.0))
+            }
+
+            fn shuffle(self, indices: Self) -> Self {
+                Portable(self.0.swizzle_dyn(indices.0))
+            }
+        }
+
+        impl Mask for PortableMask<$lanes> {
+            type Vector = Portable<$lanes>;
+            type Bitmask = $bitmask;
+
+            fn select(self, a: Portable<$lanes>, b: Portable<$lanes>) -> Portable<$lanes> {
+                Portable(self.0.select(a.0, b.0))
+            }
+
+            fn bitmask(self) -> $bitmask {
+                self.0.to_bitmask() as $bitmask
+            }
+        }
+    };
+}
+
+impl_vector!(16, u16);
+impl_vector!(32, u32);
+impl_vector!(64, u64);
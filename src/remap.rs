@@ -0,0 +1,68 @@
+use crate::{BuildError, ByteTrie, LaneCount, Lookup, SupportedLaneCount};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+// A `ByteTrie` over 16 bit symbols from a big but sparse alphabet. No trie has more distinct
+// labels than lanes, so each symbol gets a byte of its own and queries are translated before
+// they reach the trie. Unlike a `WideTrie`, the lookup itself stays a single byte comparison.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RemappedTrie<const LANES: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    symbols: BTreeMap<u16, u8>,
+    trie: ByteTrie<LANES>,
+}
+
+pub type RemappedTrie16 = RemappedTrie<16>;
+pub type RemappedTrie32 = RemappedTrie<32>;
+pub type RemappedTrie64 = RemappedTrie<64>;
+
+impl<const LANES: usize> RemappedTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // Wraps a trie whose labels are already remapped through `symbols`.
+    pub fn new(symbols: BTreeMap<u16, u8>, trie: ByteTrie<LANES>) -> Self {
+        Self { symbols, trie }
+    }
+
+    // Same as `ByteTrie::from_keys`, over symbols. Bytes are handed out in symbol order, so value
+    // ranks still follow the sorted order of the keys.
+    pub fn from_keys<I: IntoIterator<Item = Vec<u16>>>(keys: I) -> Result<Self, BuildError> {
+        let keys: Vec<Vec<u16>> = keys.into_iter().collect();
+        let distinct: BTreeSet<u16> = keys.iter().flatten().copied().collect();
+        // Every symbol labels at least one edge, so this many can't fit in any trie.
+        if distinct.len() > 256 {
+            return Err(BuildError::TooManyNodes(distinct.len()));
+        }
+        let symbols: BTreeMap<u16, u8> = distinct.into_iter().zip(0..=255).collect();
+        let remapped = keys.iter().map(|key| key.iter().map(|symbol| symbols[symbol]).collect());
+        let trie = ByteTrie::from_keys(remapped)?;
+        Ok(Self { symbols, trie })
+    }
+
+    // Same as `ByteTrie::traverse`, over symbols. A symbol that isn't in the map can't be on any
+    // path, so the query misses.
+    pub fn traverse(&self, query: &[u16; 8], query_len: usize) -> Lookup {
+        if query_len > 8 {
+            return Lookup::NoSuchPath;
+        }
+        let mut remapped = [0u8; 8];
+        for (byte, symbol) in remapped.iter_mut().zip(&query[..query_len]) {
+            match self.symbols.get(symbol) {
+                Some(&b) => *byte = b,
+                None => return Lookup::NoSuchPath,
+            }
+        }
+        self.trie.traverse(&remapped, query_len)
+    }
+
+    pub fn symbols(&self) -> &BTreeMap<u16, u8> {
+        &self.symbols
+    }
+
+    pub fn trie(&self) -> &ByteTrie<LANES> {
+        &self.trie
+    }
+}
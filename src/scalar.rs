@@ -0,0 +1,131 @@
+use crate::simd::{Mask, Vector};
+use core::ops::{BitAnd, BitOr, BitOrAssign, Shl, Shr};
+
+// Plain arrays standing in for SIMD vectors, one loop iteration per lane. Used when the `scalar`
+// feature is on or packed_simd isn't available, and in tests so they can check it against the SIMD
+// backend.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Scalar<const N: usize>([u8; N]);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ScalarMask<const N: usize>([bool; N]);
+
+impl<const N: usize> Scalar<N> {
+    fn map(self, f: impl Fn(u8) -> u8) -> Self {
+        let mut out = self.0;
+        for lane in out.iter_mut() {
+            *lane = f(*lane);
+        }
+        Scalar(out)
+    }
+
+    fn zip(self, other: Self, f: impl Fn(u8, u8) -> u8) -> Self {
+        let mut out = self.0;
+        for (lane, &b) in out.iter_mut().zip(other.0.iter()) {
+            *lane = f(*lane, b);
+        }
+        Scalar(out)
+    }
+
+    fn compare(self, other: Self, f: impl Fn(u8, u8) -> bool) -> ScalarMask<N> {
+        let mut out = [false; N];
+        for (i, lane) in out.iter_mut().enumerate() {
+            *lane = f(self.0[i], other.0[i]);
+        }
+        ScalarMask(out)
+    }
+}
+
+impl<const N: usize> BitAnd for Scalar<N> {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        self.zip(other, |a, b| a & b)
+    }
+}
+
+impl<const N: usize> BitOr for Scalar<N> {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.zip(other, |a, b| a | b)
+    }
+}
+
+impl<const N: usize> BitOrAssign for Scalar<N> {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = *self | other;
+    }
+}
+
+impl<const N: usize> Shl<u32> for Scalar<N> {
+    type Output = Self;
+
+    fn shl(self, amount: u32) -> Self {
+        self.map(|a| a << amount)
+    }
+}
+
+impl<const N: usize> Shr<u32> for Scalar<N> {
+    type Output = Self;
+
+    fn shr(self, amount: u32) -> Self {
+        self.map(|a| a >> amount)
+    }
+}
+
+// The bitmask is a `u64` regardless of `N`, which is fine since lanes past `N` are never set.
+impl<const N: usize> Vector for Scalar<N> {
+    type Mask = ScalarMask<N>;
+    type Bitmask = u64;
+
+    fn splat(byte: u8) -> Self {
+        Scalar([byte; N])
+    }
+
+    fn from_slice(bytes: &[u8]) -> Self {
+        let mut out = [0; N];
+        out.copy_from_slice(&bytes[..N]);
+        Scalar(out)
+    }
+
+    fn write_to_slice(self, bytes: &mut [u8]) {
+        bytes[..N].copy_from_slice(&self.0);
+    }
+
+    fn simd_eq(self, other: Self) -> ScalarMask<N> {
+        self.compare(other, |a, b| a == b)
+    }
+
+    fn simd_ne(self, other: Self) -> ScalarMask<N> {
+        self.compare(other, |a, b| a != b)
+    }
+
+    fn shuffle(self, indices: Self) -> Self {
+        indices.map(|i| self.0.get(i as usize).copied().unwrap_or(0))
+    }
+}
+
+impl<const N: usize> Mask for ScalarMask<N> {
+    type Vector = Scalar<N>;
+    type Bitmask = u64;
+
+    fn select(self, a: Scalar<N>, b: Scalar<N>) -> Scalar<N> {
+        let mut out = b.0;
+        for (i, lane) in out.iter_mut().enumerate() {
+            if self.0[i] {
+                *lane = a.0[i];
+            }
+        }
+        Scalar(out)
+    }
+
+    fn bitmask(self) -> u64 {
+        let mut out = 0;
+        for (i, &lane) in self.0.iter().enumerate() {
+            out |= (lane as u64) << i;
+        }
+        out
+    }
+}
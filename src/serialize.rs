@@ -0,0 +1,56 @@
+use crate::simd::NodeOf;
+use crate::{check_packed, ByteTrie, LaneCount, SupportedLaneCount};
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::de::{Error, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Both vectors go out as plain byte sequences so the format doesn't depend on the lane count. The
+// nodes are laid out like in `to_bytes`, so a 64 lane trie's are twice as long.
+#[derive(Serialize)]
+struct PackedRef<'a> {
+    edges: &'a [u8],
+    nodes: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct Packed {
+    edges: Vec<u8>,
+    nodes: Vec<u8>,
+}
+
+impl<const LANES: usize> Serialize for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (edges, _) = self.packed();
+        let mut nodes = vec![0; LANES * core::mem::size_of::<NodeOf<LANES>>()];
+        self.write_node_bytes(&mut nodes);
+        PackedRef { edges: &edges, nodes: &nodes }.serialize(serializer)
+    }
+}
+
+impl<'de, const LANES: usize> Deserialize<'de> for ByteTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // `traverse` uses the parent pointers as shuffle indices, so a corrupt blob has to be rejected
+    // here rather than trusted.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let packed = Packed::deserialize(deserializer)?;
+        let node_bytes = LANES * core::mem::size_of::<NodeOf<LANES>>();
+        for (bytes, len) in IntoIterator::into_iter([(&packed.edges, LANES), (&packed.nodes, node_bytes)]) {
+            if bytes.len() != len {
+                let expected = format!("{} bytes", len);
+                return Err(D::Error::invalid_length(bytes.len(), &expected.as_str()));
+            }
+        }
+        let nodes = Self::words_from_bytes(&packed.nodes);
+        check_packed(&packed.edges, &nodes)
+            .map_err(|e| D::Error::invalid_value(Unexpected::Other(&e.to_string()), &"a well-formed trie"))?;
+        Ok(Self::from_slices(&packed.edges, &nodes))
+    }
+}
@@ -0,0 +1,354 @@
+#[cfg(feature = "packed_simd")]
+use packed_simd::{m8x16, m8x32, m8x64, u8x16, u8x32, u8x64};
+use crate::{PARENT_BITS, PARENT_MASK};
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::ops::{BitAnd, BitOr, BitOrAssign, Shl, Shr};
+
+// Marker type for the lane count of a `ByteTrie`.
+pub struct LaneCount<const LANES: usize>;
+
+// Maps a lane count onto the vector that stores it: a packed_simd, `core::simd`, NEON, or wasm
+// SIMD128 vector, or a plain array with the `scalar` feature.
+//
+// Each node's parent pointer shares a byte with its root, value, and branch flags, which leaves
+// five bits to address lanes. That's enough for 32 lanes, so up to there a node is that byte. A
+// 64 lane trie needs a sixth pointer bit, so its nodes are `u16`s, with the pointer's bits past
+// the fifth in the byte above, and it stores them as two vectors.
+pub trait SupportedLaneCount {
+    type Vector: Vector;
+    type Node: NodeWord;
+    type Nodes: NodeLanes<Vector = Self::Vector>;
+}
+
+impl SupportedLaneCount for LaneCount<16> {
+    type Vector = backend::Vector16;
+    type Node = u8;
+    type Nodes = NodeBytes<backend::Vector16>;
+}
+
+impl SupportedLaneCount for LaneCount<32> {
+    type Vector = backend::Vector32;
+    type Node = u8;
+    type Nodes = NodeBytes<backend::Vector32>;
+}
+
+impl SupportedLaneCount for LaneCount<64> {
+    type Vector = backend::Vector64;
+    type Node = u16;
+    type Nodes = SplitNodes<backend::Vector64>;
+}
+
+// Which vectors store the lanes. When more than one backend is enabled, `scalar` wins over
+// `portable_simd`, which wins over `neon` on aarch64 and SIMD128 on wasm targets that have it,
+// which win over `packed_simd`. With none of them the scalar one is the fallback.
+#[cfg(feature = "scalar")]
+mod backend {
+    pub type Vector16 = crate::scalar::Scalar<16>;
+    pub type Vector32 = crate::scalar::Scalar<32>;
+    pub type Vector64 = crate::scalar::Scalar<64>;
+}
+
+#[cfg(all(not(feature = "scalar"), feature = "portable_simd"))]
+mod backend {
+    pub type Vector16 = crate::portable::Portable<16>;
+    pub type Vector32 = crate::portable::Portable<32>;
+    pub type Vector64 = crate::portable::Portable<64>;
+}
+
+#[cfg(all(
+    not(feature = "scalar"),
+    not(feature = "portable_simd"),
+    feature = "neon",
+    target_arch = "aarch64",
+))]
+mod backend {
+    pub type Vector16 = crate::neon::Neon16;
+    pub type Vector32 = crate::neon::Neon32;
+    pub type Vector64 = crate::neon::Neon64;
+}
+
+// SIMD128 vectors are 16 lanes wide, so 32 and 64 lane tries use the scalar backend.
+#[cfg(all(
+    not(feature = "scalar"),
+    not(feature = "portable_simd"),
+    target_arch = "wasm32",
+    target_feature = "simd128",
+))]
+mod backend {
+    pub type Vector16 = crate::wasm::Wasm128;
+    pub type Vector32 = crate::scalar::Scalar<32>;
+    pub type Vector64 = crate::scalar::Scalar<64>;
+}
+
+#[cfg(all(
+    not(feature = "scalar"),
+    not(feature = "portable_simd"),
+    not(all(feature = "neon", target_arch = "aarch64")),
+    not(all(target_arch = "wasm32", target_feature = "simd128")),
+    feature = "packed_simd",
+))]
+mod backend {
+    pub type Vector16 = packed_simd::u8x16;
+    pub type Vector32 = packed_simd::u8x32;
+    pub type Vector64 = packed_simd::u8x64;
+}
+
+#[cfg(not(any(
+    feature = "scalar",
+    feature = "portable_simd",
+    all(feature = "neon", target_arch = "aarch64"),
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    feature = "packed_simd",
+)))]
+mod backend {
+    pub type Vector16 = crate::scalar::Scalar<16>;
+    pub type Vector32 = crate::scalar::Scalar<32>;
+    pub type Vector64 = crate::scalar::Scalar<64>;
+}
+
+pub type VectorOf<const LANES: usize> = <LaneCount<LANES> as SupportedLaneCount>::Vector;
+pub type NodeOf<const LANES: usize> = <LaneCount<LANES> as SupportedLaneCount>::Node;
+pub type NodesOf<const LANES: usize> = <LaneCount<LANES> as SupportedLaneCount>::Nodes;
+
+// A node as `ByteTrie::packed` hands it out. The rest of the crate works on them widened to `u16`:
+// the node byte, with the parent pointer's bits past the fifth in the byte above, which is always
+// zero for `u8` nodes.
+pub trait NodeWord: Copy + Debug + Default + Eq + Hash + Ord + Send + Sync {
+    // Bits the parent pointer gets.
+    const PARENT_BITS: u32;
+
+    fn widen(self) -> u16;
+    // Drops whatever doesn't fit, so only for words that came from `widen` or fit the pointer.
+    fn narrow(word: u16) -> Self;
+}
+
+impl NodeWord for u8 {
+    const PARENT_BITS: u32 = PARENT_BITS;
+
+    fn widen(self) -> u16 {
+        self.into()
+    }
+
+    fn narrow(word: u16) -> Self {
+        word as u8
+    }
+}
+
+impl NodeWord for u16 {
+    const PARENT_BITS: u32 = PARENT_BITS + 8;
+
+    fn widen(self) -> u16 {
+        self
+    }
+
+    fn narrow(word: u16) -> Self {
+        word
+    }
+}
+
+// How a trie's nodes sit in vectors. Lookups only need the node bytes, for their flags, and the
+// parent pointers, as shuffle indices.
+pub trait NodeLanes: Copy + Eq + Send + Sync {
+    type Vector: Vector;
+
+    fn from_words(words: &[u16]) -> Self;
+    fn write_words(self, words: &mut [u16]);
+    fn bytes(self) -> Self::Vector;
+    // Each lane's parent pointer, which is garbage for roots and unused lanes.
+    fn pointers(self) -> Self::Vector;
+}
+
+// One node byte per lane, for up to 32 lanes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct NodeBytes<V>(pub(crate) V);
+
+// The node byte and the byte above it in separate vectors, for 64 lanes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SplitNodes<V> {
+    pub(crate) bytes: V,
+    pub(crate) high: V,
+}
+
+// Room for a plane of bytes at any supported lane count.
+const MAX_LANES: usize = 64;
+
+impl<V: Vector> NodeLanes for NodeBytes<V> {
+    type Vector = V;
+
+    fn from_words(words: &[u16]) -> Self {
+        let mut bytes = [0u8; MAX_LANES];
+        for (byte, &word) in bytes.iter_mut().zip(words) {
+            *byte = word as u8;
+        }
+        NodeBytes(V::from_slice(&bytes[..words.len()]))
+    }
+
+    fn write_words(self, words: &mut [u16]) {
+        let mut bytes = [0u8; MAX_LANES];
+        self.0.write_to_slice(&mut bytes[..words.len()]);
+        for (word, &byte) in words.iter_mut().zip(&bytes) {
+            *word = byte.into();
+        }
+    }
+
+    fn bytes(self) -> V {
+        self.0
+    }
+
+    fn pointers(self) -> V {
+        self.0 & V::splat(PARENT_MASK)
+    }
+}
+
+impl<V: Vector> NodeLanes for SplitNodes<V> {
+    type Vector = V;
+
+    fn from_words(words: &[u16]) -> Self {
+        let mut bytes = [0u8; MAX_LANES];
+        let mut high = [0u8; MAX_LANES];
+        for (i, &word) in words.iter().enumerate() {
+            let [low_byte, high_byte] = word.to_le_bytes();
+            bytes[i] = low_byte;
+            high[i] = high_byte;
+        }
+        SplitNodes { bytes: V::from_slice(&bytes[..words.len()]), high: V::from_slice(&high[..words.len()]) }
+    }
+
+    fn write_words(self, words: &mut [u16]) {
+        let mut bytes = [0u8; MAX_LANES];
+        let mut high = [0u8; MAX_LANES];
+        self.bytes.write_to_slice(&mut bytes[..words.len()]);
+        self.high.write_to_slice(&mut high[..words.len()]);
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = u16::from_le_bytes([bytes[i], high[i]]);
+        }
+    }
+
+    fn bytes(self) -> V {
+        self.bytes
+    }
+
+    fn pointers(self) -> V {
+        (self.bytes & V::splat(PARENT_MASK)) | (self.high << PARENT_BITS)
+    }
+}
+
+// The handful of vector operations the trie algorithm needs, so the same code can run over
+// different lane counts.
+pub trait Vector:
+    Copy + Eq + Send + Sync + BitAnd<Output = Self> + BitOr<Output = Self> + BitOrAssign + Shl<u32, Output = Self> + Shr<u32, Output = Self>
+{
+    type Mask: Mask<Vector = Self, Bitmask = Self::Bitmask>;
+    type Bitmask: Bitmask;
+
+    fn splat(byte: u8) -> Self;
+    fn from_slice(bytes: &[u8]) -> Self;
+    fn write_to_slice(self, bytes: &mut [u8]);
+    fn simd_eq(self, other: Self) -> Self::Mask;
+    fn simd_ne(self, other: Self) -> Self::Mask;
+
+    // `out[i] = self[indices[i]]`. Indices past the last lane give 0, like `swizzle_dyn` and the
+    // NEON and wasm table lookups, except on packed_simd: it only lowers to a single `pshufb` for
+    // 16 lanes with ssse3 enabled, and its portable fallback indexes the lanes directly, which
+    // panics.
+    fn shuffle(self, indices: Self) -> Self;
+}
+
+pub trait Mask: Copy {
+    type Vector;
+    type Bitmask;
+
+    fn select(self, a: Self::Vector, b: Self::Vector) -> Self::Vector;
+    fn bitmask(self) -> Self::Bitmask;
+}
+
+pub trait Bitmask: Copy + BitAnd<Output = Self> {
+    const BITS: u32;
+
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+
+    // All of the bits strictly below `pos`.
+    fn below(pos: u32) -> Self;
+}
+
+#[cfg(feature = "packed_simd")]
+macro_rules! impl_vector {
+    ($vector:ident, $mask:ident, $bitmask:ident) => {
+        impl Vector for $vector {
+            type Mask = $mask;
+            type Bitmask = $bitmask;
+
+            fn splat(byte: u8) -> Self {
+                $vector::splat(byte)
+            }
+
+            fn from_slice(bytes: &[u8]) -> Self {
+                $vector::from_slice_unaligned(bytes)
+            }
+
+            fn write_to_slice(self, bytes: &mut [u8]) {
+                self.write_to_slice_unaligned(bytes)
+            }
+
+            fn simd_eq(self, other: Self) -> $mask {
+                self.eq(other)
+            }
+
+            fn simd_ne(self, other: Self) -> $mask {
+                self.ne(other)
+            }
+
+            fn shuffle(self, indices: Self) -> Self {
+                self.shuffle1_dyn(indices)
+            }
+        }
+
+        impl Mask for $mask {
+            type Vector = $vector;
+            type Bitmask = $bitmask;
+
+            fn select(self, a: $vector, b: $vector) -> $vector {
+                $mask::select(self, a, b)
+            }
+
+            fn bitmask(self) -> $bitmask {
+                $mask::bitmask(self)
+            }
+        }
+
+    };
+}
+
+macro_rules! impl_bitmask {
+    ($bitmask:ident) => {
+        impl Bitmask for $bitmask {
+            const BITS: u32 = $bitmask::BITS;
+
+            fn count_ones(self) -> u32 {
+                $bitmask::count_ones(self)
+            }
+
+            fn trailing_zeros(self) -> u32 {
+                $bitmask::trailing_zeros(self)
+            }
+
+            fn below(pos: u32) -> Self {
+                (1 << pos) - 1
+            }
+        }
+    };
+}
+
+#[cfg(feature = "packed_simd")]
+impl_vector!(u8x16, m8x16, u16);
+#[cfg(feature = "packed_simd")]
+impl_vector!(u8x32, m8x32, u32);
+#[cfg(feature = "packed_simd")]
+impl_vector!(u8x64, m8x64, u64);
+
+impl_bitmask!(u16);
+impl_bitmask!(u32);
+impl_bitmask!(u64);
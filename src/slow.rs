@@ -0,0 +1,126 @@
+use crate::{BuildError, ByteTrie, Edge, LaneCount, Lookup, SupportedLaneCount};
+use alloc::collections::BTreeSet;
+use alloc::vec;
+
+// A trie of any size, kept as a `BTreeSet` of edges and walked one byte at a time. It's the same
+// walk the tests check `ByteTrie` against, so it's correct but far slower; it's here for tries
+// that don't fit in the lanes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SlowTrie {
+    edges: BTreeSet<Edge>,
+}
+
+impl SlowTrie {
+    // Checks the edges the same way `ByteTrie::try_new` does, except for how many there are and
+    // how deep they go. Ranks still have to fit in a `Lookup`, though.
+    pub fn try_new(edges: &BTreeSet<Edge>) -> Result<Self, BuildError> {
+        let mut numbers = BTreeSet::new();
+        let mut sibling_labels = BTreeSet::new();
+        for edge in edges {
+            if !numbers.insert(edge.number) {
+                return Err(BuildError::DuplicateNumber(edge.number));
+            }
+            if !sibling_labels.insert((edge.parent, edge.label)) {
+                return Err(BuildError::DuplicateSiblingLabel { parent: edge.parent, label: edge.label });
+            }
+        }
+        let num_values = edges.iter().filter(|e| e.has_value).count();
+        let num_branches = edges.iter().filter(|e| e.has_branch).count();
+        if num_values.max(num_branches) > 256 {
+            return Err(BuildError::TooManyRanks(num_values.max(num_branches)));
+        }
+
+        // Same as in `build_tree`: anything a walk down from the roots doesn't reach is cut off.
+        let mut reached = BTreeSet::new();
+        let mut stack = vec![None];
+        while let Some(parent) = stack.pop() {
+            for edge in children(edges, parent) {
+                reached.insert(edge.number);
+                stack.push(Some(edge.number));
+            }
+        }
+        if let Some(orphan) = edges.iter().filter(|e| !reached.contains(&e.number)).min_by_key(|e| e.number) {
+            return Err(BuildError::OrphanEdge { number: orphan.number, parent: orphan.parent.unwrap() });
+        }
+        Ok(Self { edges: edges.clone() })
+    }
+
+    // Same as `ByteTrie::traverse_slice`, for a query of any length.
+    pub fn traverse(&self, query: &[u8]) -> Lookup {
+        traverse(&self.edges, query)
+    }
+
+    pub fn edges(&self) -> &BTreeSet<Edge> {
+        &self.edges
+    }
+}
+
+// A `ByteTrie` when the edges fit, and a `SlowTrie` when they don't, with the same lookups either
+// way.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Trie<const LANES: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    Simd(ByteTrie<LANES>),
+    Fallback(SlowTrie),
+}
+
+pub type Trie16 = Trie<16>;
+pub type Trie32 = Trie<32>;
+pub type Trie64 = Trie<64>;
+
+impl<const LANES: usize> Trie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // Only errors on edges that don't make a trie at any size.
+    pub fn build(edges: &BTreeSet<Edge>) -> Result<Self, BuildError> {
+        match ByteTrie::try_new(edges) {
+            Ok(trie) => Ok(Trie::Simd(trie)),
+            Err(_) => SlowTrie::try_new(edges).map(Trie::Fallback),
+        }
+    }
+
+    // Same as `ByteTrie::traverse_slice`, but any length of query works on a fallback trie.
+    pub fn traverse(&self, query: &[u8]) -> Lookup {
+        match self {
+            Trie::Simd(trie) => trie.traverse_slice(query),
+            Trie::Fallback(trie) => trie.traverse(query),
+        }
+    }
+}
+
+fn children(edges: &BTreeSet<Edge>, parent: Option<usize>) -> impl Iterator<Item = &Edge> {
+    let end = Some(parent.map(|p| p + 1).unwrap_or(0));
+    edges.range(Edge::bound(parent)..Edge::bound(end))
+}
+
+// The edge `query` ends on, or the position of the first byte with no edge to follow. The empty
+// query ends at the root, which has no edge.
+pub(crate) fn walk<'a>(edges: &'a BTreeSet<Edge>, query: &[u8]) -> Result<Option<&'a Edge>, usize> {
+    let mut current = None;
+    for (i, &byte) in query.iter().enumerate() {
+        match children(edges, current.map(|e: &Edge| e.number)).find(|e| e.label == byte) {
+            Some(e) => current = Some(e),
+            None => return Err(i),
+        }
+    }
+    Ok(current)
+}
+
+// What `ByteTrie::traverse_slice` returns for `query`, minus the limit on its length.
+pub(crate) fn traverse(edges: &BTreeSet<Edge>, query: &[u8]) -> Lookup {
+    let e = match walk(edges, query) {
+        Ok(Some(e)) => e,
+        _ => return Lookup::NoSuchPath,
+    };
+    let value_rank = edges.iter().filter(|other| other.has_value && other.number < e.number).count() as u8;
+    let branch_rank = edges.iter().filter(|other| other.has_branch && other.number < e.number).count() as u8;
+    match (e.has_value, e.has_branch) {
+        (true, true) => Lookup::ValueAndBranch(value_rank, branch_rank),
+        (true, false) => Lookup::Value(value_rank),
+        (false, true) => Lookup::Branch(branch_rank),
+        (false, false) => Lookup::Exists,
+    }
+}
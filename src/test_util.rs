@@ -0,0 +1,126 @@
+// A slow reference trie for checking a `ByteTrie` against, available to other crates with the
+// `test-util` feature. It walks the edges one byte at a time like a `SlowTrie`, but doesn't check
+// them, so tests can build it from anything.
+use crate::{slow, ByteTrie, Edge, LaneCount, Lookup, Precedence, SupportedLaneCount};
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub struct TestTree {
+    pub(crate) edges: BTreeSet<Edge>,
+}
+
+// One node of a tree to build with `TestTree::from_spec`, along with everything under it. Specs
+// are plain nested `Vec`s, so property testing libraries can shrink a failing one down to a
+// minimal tree by dropping children and clearing flags.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NodeSpec {
+    pub label: u8,
+    pub has_value: bool,
+    pub has_branch: bool,
+    pub children: Vec<NodeSpec>,
+}
+
+impl TestTree {
+    // Builds the tree with `roots` at the top, numbering edges in preorder. A node whose label
+    // repeats an earlier sibling's is dropped along with its children, so every spec gives a valid
+    // tree. Nothing limits its size or depth, though, so it might not fit in a `ByteTrie`.
+    pub fn from_spec(roots: &[NodeSpec]) -> Self {
+        let mut edges = BTreeSet::new();
+        add_children(&mut edges, None, roots);
+        Self { edges }
+    }
+
+    // Takes `edges` as they are, without checking them.
+    pub fn new(edges: &BTreeSet<Edge>) -> Self {
+        Self { edges: edges.clone() }
+    }
+
+    pub fn edges(&self) -> &BTreeSet<Edge> {
+        &self.edges
+    }
+
+    // Every path from the root that ends at a node, in preorder.
+    pub fn paths(&self) -> Vec<Vec<u8>> {
+        let mut paths = vec![];
+        let mut stack = vec![(None, vec![])];
+        while let Some((parent, path)) = stack.pop() {
+            let start = Edge::bound(parent);
+            let end = Edge::bound(Some(parent.map(|n| n + 1).unwrap_or(0)));
+            for edge in self.edges.range(start..end).rev() {
+                let mut child = path.clone();
+                child.push(edge.label);
+                stack.push((Some(edge.number), child));
+            }
+            if parent.is_some() {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    // What `ByteTrie::traverse_slice` should return for `query`.
+    pub fn traverse(&self, query: &[u8]) -> Lookup {
+        slow::traverse(&self.edges, query)
+    }
+
+    // What `ByteTrie::traverse_with_precedence` should return for `query`.
+    pub fn traverse_with_precedence(&self, query: &[u8], precedence: Precedence) -> Lookup {
+        match self.traverse(query) {
+            Lookup::ValueAndBranch(value_rank, branch_rank) => match precedence {
+                Precedence::Value => Lookup::Value(value_rank),
+                Precedence::Branch => Lookup::Branch(branch_rank),
+            },
+            lookup => lookup,
+        }
+    }
+
+    // Length of the longest prefix of `query` that's a path in the tree.
+    pub fn prefix_len(&self, query: &[u8]) -> usize {
+        slow::walk(&self.edges, query).err().unwrap_or(query.len())
+    }
+}
+
+fn add_children(edges: &mut BTreeSet<Edge>, parent: Option<usize>, specs: &[NodeSpec]) {
+    let mut labels = BTreeSet::new();
+    for spec in specs {
+        if !labels.insert(spec.label) {
+            continue;
+        }
+        let number = edges.len();
+        edges.insert(Edge { parent, label: spec.label, number, has_value: spec.has_value, has_branch: spec.has_branch });
+        add_children(edges, Some(number), &spec.children);
+    }
+}
+
+// Panics unless `fast` finds the same thing as a `TestTree` of `edges` at every path in the tree
+// and at every byte past each of them, the same check the crate's own random tests make. Paths no
+// longer than 8 go through `traverse` as well as `traverse_slice`.
+pub fn assert_equivalent<const LANES: usize>(fast: &ByteTrie<LANES>, edges: &BTreeSet<Edge>)
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    let slow = TestTree::new(edges);
+    assert_eq!(fast.len(), edges.len(), "trie has {} nodes but there are {} edges", fast.len(), edges.len());
+    let check = |query: &[u8]| {
+        let expected = slow.traverse(query);
+        assert_eq!(fast.traverse_slice(query), expected, "lookups differ for {:?}", query);
+        if query.len() <= 8 {
+            let mut padded = [0u8; 8];
+            padded[..query.len()].copy_from_slice(query);
+            assert_eq!(fast.traverse(&padded, query.len()), expected, "lookups differ for {:?}", query);
+        }
+    };
+    let mut query = vec![];
+    for path in core::iter::once(vec![]).chain(slow.paths()) {
+        if !path.is_empty() {
+            check(&path);
+        }
+        query.clone_from(&path);
+        query.push(0);
+        for byte in 0..=255 {
+            *query.last_mut().unwrap() = byte;
+            check(&query);
+        }
+    }
+}
@@ -1,17 +1,23 @@
-use super::{ByteTrie16, Edge, Lookup};
+use super::scalar::Scalar;
+use super::simd::{NodeLanes, NodeWord, SplitNodes, Vector};
+use super::{
+    reverse_query, BuildError, BuildScratch, ByteMap16, ByteTrie, ByteTrie16, ByteTrie32, ByteTrie64, ByteTrieDepth,
+    CursorState, DualTrie8, Edge, LaneCount, Lanes, LinkedTrie16, Lookup, LookupKind, Precedence, RemappedTrie16,
+    SupportedLaneCount, Trie16, TrieArena16, TrieBuilder, TrieStats, ValidationError, WideEdge, WideTrie16, WideTrie64,
+};
+use super::test_util::{assert_equivalent, NodeSpec, TestTree};
 
 use rand_distr::{Distribution, Exp};
 use rand::{SeedableRng, Rng};
 use rand_isaac::IsaacRng;
-use std::collections::{VecDeque, BTreeSet, HashMap};
-
-pub struct TestTree {
-    edges: BTreeSet<Edge>,
-}
+use std::collections::{VecDeque, BTreeSet, HashMap, HashSet};
+use std::convert::TryInto;
 
+// Random trees for the differential tests. The rest of `TestTree` is in `test_util` so other
+// crates can use it too.
 impl TestTree {
-    pub fn generate(rng: &mut impl Rng) -> Self {
-        let num_children_dist = Exp::new(0.25).unwrap();
+    pub fn generate(rng: &mut impl Rng, max_edges: usize, max_depth: usize, mean_children: f64) -> Self {
+        let num_children_dist = Exp::new(1.0 / mean_children).unwrap();
 
         let mut queue = VecDeque::new();
         queue.push_back((None, 1));
@@ -19,7 +25,7 @@ impl TestTree {
         let mut edges = BTreeSet::new();
 
         while let Some((parent, depth)) = queue.pop_front() {
-            if depth > 8 {
+            if depth > max_depth {
                 continue;
             }
 
@@ -28,7 +34,7 @@ impl TestTree {
             let mut labels = BTreeSet::new();
 
             for _ in 0..num_children {
-                if edges.len() >= 16 {
+                if edges.len() >= max_edges {
                     break;
                 }
                 let mut label = rng.gen();
@@ -39,12 +45,14 @@ impl TestTree {
 
                 let has_value_pr: f64 = rng.gen();
                 let has_value = has_value_pr <= 0.4;
+                let has_branch_pr: f64 = rng.gen();
+                let has_branch = has_branch_pr <= 0.2;
                 let edge = Edge {
                     parent,
                     label,
                     number,
                     has_value,
-                    has_branch: false,
+                    has_branch,
                 };
                 edges.insert(edge);
                 if !has_value {
@@ -56,36 +64,120 @@ impl TestTree {
 
         Self { edges }
     }
+}
 
-    fn traverse(&self, query: &[u8]) -> Lookup {
-        let mut cur_node = None;
+#[test]
+fn test_random() {
+    check_random::<16>(8, 4.0);
+}
 
-        for &byte in query {
-            let start = Edge::bound(cur_node);
-            let end = Edge::bound(Some(cur_node.map(|n| n + 1).unwrap_or(0)));
+#[test]
+fn test_random32() {
+    check_random::<32>(8, 4.0);
+}
 
-            if let Some(e) = self.edges.range(start..end).find(|e| e.label == byte) {
-                cur_node = Some(e.number);
-                continue;
-            }
-            return Lookup::None;
-        }
+#[test]
+fn test_random64() {
+    check_random::<64>(8, 4.0);
+}
 
-        let e = self.edges.iter().find(|e| Some(e.number) == cur_node).unwrap();
-        if e.has_branch {
-            let branch_rank = self.edges.iter().filter(|e| e.has_branch && Some(e.number) < cur_node).count();
-            return Lookup::Branch(branch_rank as u8);
+#[test]
+fn test_random_deep() {
+    // Fewer children per node so paths actually get past depth 8 before running out of lanes.
+    check_random::<16>(16, 2.0);
+    check_random::<32>(16, 2.0);
+    check_random::<64>(16, 2.0);
+}
+
+// Checks every way of running `query` against the fast trie agrees with the slow one.
+fn check_query<const LANES: usize>(slow: &TestTree, fast: &ByteTrie<LANES>, query: &[u8; 16], query_len: usize) -> Lookup
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // `query` has leftovers from earlier paths past `query_len`, which the lookups only accept as
+    // zeros.
+    let mut padded = [0u8; 16];
+    padded[..query_len].copy_from_slice(&query[..query_len]);
+    let slow_query = slow.traverse(&query[..query_len]);
+    if query_len <= 8 {
+        let mut short_query = [0u8; 8];
+        short_query.copy_from_slice(&padded[..8]);
+        assert_eq!(slow_query, fast.traverse(&short_query, query_len));
+        let depth = if slow_query == Lookup::NoSuchPath { 0 } else { query_len };
+        assert_eq!((slow.traverse(&query[..query_len]), depth), fast.traverse_with_depth(&short_query, query_len));
+        let is_key = matches!(slow_query, Lookup::Value(_) | Lookup::ValueAndBranch(..));
+        assert_eq!(is_key, fast.contains_key(&short_query, query_len));
+        let value_rank = match slow_query {
+            Lookup::Value(rank) | Lookup::ValueAndBranch(rank, _) => Some(rank),
+            _ => None,
+        };
+        assert_eq!(value_rank, fast.value_rank(&short_query, query_len));
+        let longest = (1..=query_len).rev().find_map(|len| match slow.traverse(&query[..len]) {
+            Lookup::Value(rank) | Lookup::ValueAndBranch(rank, _) => Some((rank, len)),
+            _ => None,
+        });
+        assert_eq!(longest, fast.longest_prefix_match(&short_query, query_len));
+        assert_eq!(slow.prefix_len(&query[..query_len]), fast.prefix_len(&short_query, query_len));
+        if query_len > 0 {
+            let (prefix, last) = (&query[..query_len - 1], query[query_len - 1]);
+            let ge = if slow.prefix_len(prefix) == prefix.len() {
+                let mut path = prefix.to_vec();
+                path.push(0);
+                let mut labels = (last..=255).filter(|&label| {
+                    path[query_len - 1] = label;
+                    slow.prefix_len(&path) == query_len
+                });
+                labels.next().map(|label| {
+                    path[query_len - 1] = label;
+                    (label, slow.traverse(&path))
+                })
+            } else {
+                None
+            };
+            assert_eq!(ge, fast.traverse_ge(&short_query, query_len));
         }
-        if e.has_value {
-            let value_rank = self.edges.iter().filter(|e| e.has_value && Some(e.number) < cur_node).count();
-            return Lookup::Value(value_rank as u8);
+        let explained = match slow.prefix_len(&query[..query_len]) {
+            matched if matched < query_len => Err(matched),
+            _ => Ok(slow.traverse(&query[..query_len])),
+        };
+        assert_eq!(explained, fast.explain(&short_query, query_len));
+        for &precedence in &[Precedence::Value, Precedence::Branch] {
+            assert_eq!(
+                slow.traverse_with_precedence(&query[..query_len], precedence),
+                fast.traverse_with_precedence(&short_query, query_len, precedence)
+            );
+        }
+    }
+    assert_eq!(slow_query, fast.traverse_deep(&padded, query_len));
+    let mut cursor = fast.cursor();
+    for (i, &byte) in query[..query_len].iter().enumerate() {
+        let expected = match slow.traverse(&query[..=i]) {
+            Lookup::Exists => CursorState::Partial,
+            Lookup::NoSuchPath => CursorState::Dead,
+            lookup => CursorState::Match(lookup),
         };
-        Lookup::None
+        assert_eq!(cursor.step(byte), expected);
     }
+    assert_eq!(slow_query, fast.traverse_slice(&query[..query_len]));
+
+    // Run the same lanes through the scalar backend, which is a no-op check when it's also the
+    // one `fast` uses. Split nodes hold any lane count's, with the high byte all zeros up to 32.
+    let (edges, nodes) = fast.packed();
+    let nodes = SplitNodes::<Scalar<LANES>>::from_words(&nodes.map(NodeWord::widen));
+    let lanes = Lanes::new(nodes, Scalar::<LANES>::from_slice(&edges));
+    if query_len <= 8 {
+        let mut short_query = [0u8; 8];
+        short_query.copy_from_slice(&padded[..8]);
+        assert_eq!(slow_query, super::traverse(&lanes, &short_query, query_len));
+    }
+    assert_eq!(slow_query, super::traverse_deep(&lanes, &padded, query_len));
+    slow_query
 }
 
-#[test]
-fn test_random() {
+fn check_random<const LANES: usize>(max_depth: usize, mean_children: f64)
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
     let num_iters: usize = std::env::var("NUM_ITERS")
         .map(|s| s.parse().unwrap())
         .unwrap_or(1);
@@ -94,8 +186,8 @@ fn test_random() {
         println!("Seed: {:02x?}", seed);
         let mut rng = IsaacRng::from_seed(seed);
 
-        let slow = TestTree::generate(&mut rng);
-        let fast = ByteTrie16::new(&slow.edges);
+        let slow = TestTree::generate(&mut rng, LANES, max_depth, mean_children);
+        let fast = ByteTrie::<LANES>::new(&slow.edges);
 
         println!("Edges:");
         for edge in &slow.edges {
@@ -104,7 +196,7 @@ fn test_random() {
 
         // First iterate over all of the paths in the tree.
         let mut stack: Vec<(Option<usize>, bool)> = vec![(None, true)];
-        let mut query = [0u8; 8];
+        let mut query = [0u8; 16];
         let mut query_len = 0;
 
         let mut labels = HashMap::new();
@@ -121,10 +213,13 @@ fn test_random() {
                     query[query_len] = label;
                     query_len += 1;
 
-                    let slow_query = slow.traverse(&query[..query_len]);
-                    let fast_query = fast.traverse(&query, query_len);
+                    let slow_query = check_query(&slow, &fast, &query, query_len);
                     println!("query: {:?} -> {:?}", &query[..query_len], slow_query);
-                    assert_eq!(slow_query, fast_query);
+                    if query_len <= 8 {
+                        let mut short_query = [0u8; 8];
+                        short_query[..query_len].copy_from_slice(&query[..query_len]);
+                        assert!(fast.contains_prefix(&short_query, query_len));
+                    }
                     keys.insert(query[..query_len].to_owned());
                 }
 
@@ -135,27 +230,138 @@ fn test_random() {
                 for edge in slow.edges.range(start..end) {
                     stack.push((Some(edge.number), true));
                 }
-            } else {
-                if let Some(..) = node {
-                    query_len -= 1;
+            } else if node.is_some() {
+                query_len -= 1;
+            }
+        }
+
+        assert_equivalent(&fast, &slow.edges);
+        assert_eq!(fast.validate(), Ok(()));
+        let sorted: Vec<Edge> = slow.edges.iter().copied().collect();
+        assert_eq!(ByteTrie::<LANES>::from_sorted_edges(&sorted), fast);
+        assert_eq!(fast.len(), slow.edges.len());
+        assert_eq!(fast.iter_edges().collect::<BTreeSet<_>>(), slow.edges);
+        assert_eq!(fast.into_iter().collect::<ByteTrie<LANES>>(), fast);
+        assert_eq!(fast.is_empty(), slow.edges.is_empty());
+        assert_eq!(fast.num_values() as usize, slow.edges.iter().filter(|e| e.has_value).count());
+        assert_eq!(fast.num_branches() as usize, slow.edges.iter().filter(|e| e.has_branch).count());
+        assert_eq!(fast.max_depth(), keys.iter().map(|k| k.len()).max().unwrap_or(0));
+        for dfs_ix in 0..=LANES as u8 {
+            let edge = slow.edges.iter().find(|e| e.number == dfs_ix as usize);
+            assert_eq!(fast.node_label(dfs_ix), edge.map(|e| e.label));
+            assert_eq!(fast.node_parent(dfs_ix), edge.and_then(|e| e.parent).map(|p| p as u8));
+            assert_eq!(fast.is_root_child(dfs_ix), edge.is_some_and(|e| e.parent.is_none()));
+        }
+
+        // `keys` should list exactly the paths that end at a value or branch.
+        let mut fast_keys = BTreeSet::new();
+        for (key, lookup) in fast.keys() {
+            assert_eq!(slow.traverse(&key), lookup);
+            assert!(fast_keys.insert(key));
+        }
+        let slow_keys = keys.iter().filter(|k| !matches!(slow.traverse(k), Lookup::NoSuchPath | Lookup::Exists)).cloned().collect();
+        assert_eq!(fast_keys, slow_keys);
+
+        let entries = fast.value_entries();
+        assert_eq!(entries.len(), fast.num_values() as usize);
+        for (i, (rank, path)) in entries.iter().enumerate() {
+            assert_eq!(*rank as usize, i);
+            assert!(matches!(fast.traverse_slice(path), Lookup::Value(r) | Lookup::ValueAndBranch(r, _) if r == *rank));
+        }
+
+        let mut num_branches = 0;
+        for (key, rank) in fast.branches() {
+            assert!(matches!(slow.traverse(&key), Lookup::Branch(r) | Lookup::ValueAndBranch(_, r) if r == rank));
+            num_branches += 1;
+        }
+        assert_eq!(num_branches, fast.num_branches());
+
+        // Wildcard each position of every short path in turn, and check against the paths that fit.
+        for key in keys.iter().filter(|k| k.len() <= 8) {
+            for wild in 0..key.len() {
+                let mut pattern = [None; 8];
+                for (slot, &byte) in pattern.iter_mut().zip(key) {
+                    *slot = Some(byte);
                 }
+                pattern[wild] = None;
+                let fits = |k: &&Vec<u8>| k.len() == key.len() && (0..k.len()).all(|i| i == wild || k[i] == key[i]);
+                let expected: Vec<Lookup> =
+                    keys.iter().filter(fits).map(|k| slow.traverse(k)).filter(|l| !matches!(l, Lookup::NoSuchPath | Lookup::Exists)).collect();
+                let lookups = fast.traverse_wildcard(&pattern, key.len());
+                assert_eq!(lookups.len(), expected.len());
+                assert!(expected.iter().all(|l| lookups.contains(l)));
             }
         }
 
+        let first_bytes: BTreeSet<u8> = keys.iter().map(|k| k[0]).collect();
+        let mask = fast.first_byte_mask();
+        assert!((0..=255u8).all(|b| mask[b as usize] == first_bytes.contains(&b)));
+
+        // Every path falls back to the value on the closest of its own prefixes.
+        for key in keys.iter().filter(|k| k.len() <= 8) {
+            let mut query = [0u8; 8];
+            query[..key.len()].copy_from_slice(key);
+            let expected = (1..=key.len()).rev().find_map(|len| match slow.traverse(&key[..len]) {
+                Lookup::Value(rank) | Lookup::ValueAndBranch(rank, _) => Some(rank),
+                _ => None,
+            });
+            assert_eq!(fast.value_or_ancestor(&query, key.len()), expected);
+        }
+
+        // And every position at once, allowing one mismatch anywhere.
+        for key in keys.iter().filter(|k| k.len() <= 8) {
+            let mut query = [0u8; 8];
+            query[..key.len()].copy_from_slice(key);
+            let near = |k: &&Vec<u8>| k.len() == key.len() && k.iter().zip(key.iter()).filter(|(a, b)| a != b).count() <= 1;
+            let expected: Vec<Lookup> =
+                keys.iter().filter(near).map(|k| slow.traverse(k)).filter(|l| !matches!(l, Lookup::NoSuchPath | Lookup::Exists)).collect();
+            let lookups = fast.traverse_fuzzy1(&query, key.len());
+            assert_eq!(lookups.len(), expected.len());
+            assert!(expected.iter().all(|l| lookups.contains(l)));
+        }
+
+        // Every path is a prefix, so check `values_with_prefix` against the values under each one.
+        for prefix in keys.iter().filter(|k| k.len() <= 8) {
+            let mut expected = vec![];
+            for key in keys.iter().filter(|k| k.starts_with(prefix)) {
+                if let Lookup::Value(rank) | Lookup::ValueAndBranch(rank, _) = slow.traverse(key) {
+                    expected.push(rank);
+                }
+            }
+            expected.sort_unstable();
+            let mut query = [0u8; 8];
+            query[..prefix.len()].copy_from_slice(prefix);
+            assert_eq!(fast.values_with_prefix(&query, prefix.len()), expected);
+
+            let mut expected = vec![];
+            for key in keys.iter().filter(|k| k.len() > prefix.len() && k.starts_with(prefix)) {
+                if let Lookup::Branch(rank) | Lookup::ValueAndBranch(_, rank) = slow.traverse(key) {
+                    expected.push(rank);
+                }
+            }
+            expected.sort_unstable();
+            assert_eq!(fast.branches_under_prefix(&query, prefix.len()), expected);
+
+            let children = keys.iter().filter(|k| k.len() == prefix.len() + 1 && k.starts_with(prefix));
+            let next: BTreeSet<u8> = children.map(|k| k[prefix.len()]).collect();
+            assert_eq!(fast.next_bytes(&query, prefix.len()), next.into_iter().collect::<Vec<u8>>());
+        }
+
         // Try a key that isn't in the tree.
         for query_v in &keys {
-            let mut query = [0u8; 8];
+            let mut query = [0u8; 16];
             let query_len = query_v.len();
             query[..query_len].copy_from_slice(&query_v[..]);
 
             for _ in 0..=255 {
                 query[query_len - 1] = query[query_len - 1].wrapping_add(1);
                 if !keys.contains(&query[..query_len]) {
-                    let slow_query = slow.traverse(&query[..query_len]);
-                    let fast_query = fast.traverse(&query, query_len);
+                    let slow_query = check_query(&slow, &fast, &query, query_len);
                     println!("negative query: {:?} -> {:?}", &query[..query_len], slow_query);
-                    assert_eq!(slow_query, fast_query);
-                    assert_eq!(slow_query, Lookup::None);
+                    assert_eq!(slow_query, Lookup::NoSuchPath);
+                    if query_len <= 8 {
+                        assert!(!fast.contains_prefix(query[..8].try_into().unwrap(), query_len));
+                    }
                     break;
                 }
             }
@@ -165,8 +371,8 @@ fn test_random() {
 
 // connected
 // tree
-// max depth 8
-// max nodes 16
+// max depth 16
+// max nodes LANES (16, 32, or 64)
 
 
 
@@ -189,5 +395,1606 @@ fn test_tree() {
     ];
     let edges = e.iter().cloned().collect();
     let t = ByteTrie16::new(&edges);
-    assert_eq!(t.traverse(&[0, 1, 4, 0, 0, 0, 0, 0], 1), Lookup::None);
+    assert_eq!(t.traverse(&[0, 0, 0, 0, 0, 0, 0, 0], 1), Lookup::NoSuchPath);
+    assert_eq!(t.traverse(&[0, 0, 0, 0, 0, 0, 0, 0], 0), Lookup::NoSuchPath);
+    assert_eq!(t.traverse(&[1, 3, 0, 0, 0, 0, 0, 0], 9), Lookup::NoSuchPath);
+    assert_eq!(t.traverse(&[1, 3, 0, 0, 0, 0, 0, 0], usize::MAX), Lookup::NoSuchPath);
+
+    let expected = "\
+ByteTrie {
+    1 (lane 0)
+        3 (lane 2, parent 0) value
+        4 (lane 3, parent 0) value
+    2 (lane 1)
+        5 (lane 4, parent 1)
+            7 (lane 5, parent 4) value
+}";
+    assert_eq!(format!("{:?}", t), expected);
+}
+
+#[test]
+fn test_to_dot() {
+    let e = &[
+        Edge { parent: None,    label: 1, number: 0, has_value: false, has_branch: true  },
+        Edge { parent: None,    label: 2, number: 1, has_value: true,  has_branch: false },
+        Edge { parent: Some(0), label: 3, number: 2, has_value: true,  has_branch: true  },
+    ];
+    let t = ByteTrie16::new(&e.iter().cloned().collect());
+    let expected = "\
+digraph {
+    root [shape=point];
+    n0 [label=\"0: 1\", shape=circle, style=dashed];
+    root -> n0;
+    n1 [label=\"1: 2\", shape=doublecircle, style=solid];
+    root -> n1;
+    n2 [label=\"2: 3\", shape=doublecircle, style=dashed];
+    n0 -> n2;
+}";
+    assert_eq!(t.to_dot(), expected);
+    assert_eq!(ByteTrie16::empty().to_dot(), "digraph {\n    root [shape=point];\n}");
+}
+
+#[test]
+fn test_from_spec() {
+    let leaf = |label, has_value| NodeSpec { label, has_value, ..NodeSpec::default() };
+    let node = |label, children| NodeSpec { label, children, ..NodeSpec::default() };
+
+    // The tree from `test_tree`, plus a second 2 under the root that gets dropped with its child.
+    let spec = vec![
+        node(1, vec![leaf(3, true), leaf(4, true)]),
+        node(2, vec![node(5, vec![leaf(7, true)])]),
+        node(2, vec![leaf(6, true)]),
+    ];
+    let slow = TestTree::from_spec(&spec);
+    let e = &[
+        Edge { parent: None,    label: 1, number: 0, has_value: false, has_branch: false },
+        Edge { parent: Some(0), label: 3, number: 1, has_value: true,  has_branch: false },
+        Edge { parent: Some(0), label: 4, number: 2, has_value: true,  has_branch: false },
+        Edge { parent: None,    label: 2, number: 3, has_value: false, has_branch: false },
+        Edge { parent: Some(3), label: 5, number: 4, has_value: false, has_branch: false },
+        Edge { parent: Some(4), label: 7, number: 5, has_value: true,  has_branch: false },
+    ];
+    assert_eq!(slow.edges(), &e.iter().cloned().collect::<BTreeSet<_>>());
+
+    let fast = ByteTrie16::new(slow.edges());
+    for query in &[&[1, 3][..], &[1, 4], &[2, 5, 7], &[2, 6], &[2, 5]] {
+        let mut padded = [0u8; 16];
+        padded[..query.len()].copy_from_slice(query);
+        check_query(&slow, &fast, &padded, query.len());
+    }
+    assert_eq!(TestTree::from_spec(&[]).edges(), &BTreeSet::new());
+}
+
+#[test]
+fn test_assert_equivalent() {
+    let edges = crate::trie! { 1 => { 3 = value, 4 = value }, 2 => { 5 => { 7 = value + branch } } };
+    let slow = TestTree::new(&edges);
+    let paths: Vec<Vec<u8>> = vec![vec![1], vec![1, 3], vec![1, 4], vec![2], vec![2, 5], vec![2, 5, 7]];
+    assert_eq!(slow.paths(), paths);
+    assert_equivalent(&ByteTrie16::new(&edges), &edges);
+    assert_equivalent(&ByteTrie16::empty(), &BTreeSet::new());
+}
+
+#[test]
+#[should_panic(expected = "lookups differ for [2, 5, 7]")]
+fn test_assert_equivalent_mismatch() {
+    let edges = crate::trie! { 1 => { 3 = value, 4 = value }, 2 => { 5 => { 7 = value + branch } } };
+    let other = crate::trie! { 1 => { 3 = value, 4 = value }, 2 => { 5 => { 7 = value } } };
+    assert_equivalent(&ByteTrie16::new(&other), &edges);
+}
+
+#[test]
+fn test_explain() {
+    let t = ByteTrie16::from_keys(vec![b"cat".to_vec(), b"cow".to_vec()]).unwrap();
+    assert_eq!(t.explain(b"cat\0\0\0\0\0", 3), Ok(Lookup::Value(0)));
+    assert_eq!(t.explain(b"co\0\0\0\0\0\0", 2), Ok(Lookup::Exists));
+    assert_eq!(t.explain(b"cut\0\0\0\0\0", 3), Err(1));
+    assert_eq!(t.explain(b"dog\0\0\0\0\0", 3), Err(0));
+    assert_eq!(t.explain(b"cats\0\0\0\0", 4), Err(3));
+    assert_eq!(t.explain(&[0; 8], 0), Ok(Lookup::NoSuchPath));
+}
+
+#[test]
+fn test_traverse_ge() {
+    let t = ByteTrie16::from_keys(vec![b"ab".to_vec(), b"ad".to_vec(), b"af".to_vec(), b"b".to_vec()]).unwrap();
+    assert_eq!(t.traverse_exact(b"ab\0\0\0\0\0\0", 2), Lookup::Value(0));
+    assert_eq!(t.traverse_ge(b"ab\0\0\0\0\0\0", 2), Some((b'b', Lookup::Value(0))));
+    assert_eq!(t.traverse_ge(b"ac\0\0\0\0\0\0", 2), Some((b'd', Lookup::Value(1))));
+    assert_eq!(t.traverse_ge(b"a\0\0\0\0\0\0\0", 2), Some((b'b', Lookup::Value(0))));
+    assert_eq!(t.traverse_ge(b"ag\0\0\0\0\0\0", 2), None);
+    assert_eq!(t.traverse_ge(b"cb\0\0\0\0\0\0", 2), None);
+    // Prefixes that miss partway, even though the last byte would match a root's label.
+    assert_eq!(t.traverse_ge(b"axb\0\0\0\0\0", 3), None);
+    assert_eq!(t.traverse_ge(b"abxa\0\0\0\0", 4), None);
+    let t32 = ByteTrie32::from_keys(vec![b"ab".to_vec(), b"b".to_vec()]).unwrap();
+    assert_eq!(t32.traverse_ge(b"cb\0\0\0\0\0\0", 2), None);
+    assert_eq!(t32.traverse_ge(b"axb\0\0\0\0\0", 3), None);
+    // A single byte compares against the roots, which don't have to end at anything.
+    assert_eq!(t.traverse_ge(b"\0\0\0\0\0\0\0\0", 1), Some((b'a', Lookup::Exists)));
+    assert_eq!(t.traverse_ge(b"c\0\0\0\0\0\0\0", 1), None);
+    assert_eq!(t.traverse_ge(&[0; 8], 0), None);
+}
+
+#[test]
+fn test_lookup_kind() {
+    assert_eq!(<(LookupKind, u16)>::from(Lookup::NoSuchPath), (LookupKind::NoSuchPath, 0));
+    assert_eq!(<(LookupKind, u16)>::from(Lookup::Exists), (LookupKind::Exists, 0));
+    assert_eq!(<(LookupKind, u16)>::from(Lookup::Branch(3)), (LookupKind::Branch, 3));
+    assert_eq!(<(LookupKind, u16)>::from(Lookup::Value(31)), (LookupKind::Value, 31));
+    assert_eq!(<(LookupKind, u16)>::from(Lookup::ValueAndBranch(2, 5)), (LookupKind::ValueAndBranch, 0x0205));
+    assert_eq!(<(LookupKind, u16)>::from(Lookup::ValueAndBranch(63, 63)), (LookupKind::ValueAndBranch, 0x3F3F));
+
+    // A full 64 lane trie reaches rank 63: one byte keys up to 62, which is also a branch, and a
+    // child under it.
+    let mut keys: Vec<Vec<u8>> = (0..=62).map(|b| vec![b]).collect();
+    keys.push(vec![62, 0]);
+    let t = ByteTrie64::from_keys(keys).unwrap().with_key(&[62], true, true).unwrap();
+    assert_eq!(<(LookupKind, u16)>::from(t.traverse_slice(&[62])), (LookupKind::ValueAndBranch, 0x3E00));
+    assert_eq!(<(LookupKind, u16)>::from(t.traverse_slice(&[62, 0])), (LookupKind::Value, 63));
+
+    // The same for any width.
+    let keys = || vec![b"a".to_vec(), b"ab".to_vec()];
+    let narrow: (LookupKind, u16) = ByteTrie16::from_keys(keys()).unwrap().traverse_slice(b"ab").into();
+    let wide: (LookupKind, u16) = ByteTrie32::from_keys(keys()).unwrap().traverse_slice(b"ab").into();
+    let widest: (LookupKind, u16) = ByteTrie64::from_keys(keys()).unwrap().traverse_slice(b"ab").into();
+    assert_eq!(narrow, (LookupKind::Value, 1));
+    assert_eq!(narrow, wide);
+    assert_eq!(narrow, widest);
+}
+
+#[test]
+fn test_traverse_slice() {
+    let e = &[
+        Edge { parent: None,    label: 1, number: 0, has_value: false, has_branch: false },
+        Edge { parent: Some(0), label: 2, number: 1, has_value: true,  has_branch: false },
+    ];
+    let edges = e.iter().cloned().collect();
+    let t = ByteTrie16::new(&edges);
+    assert_eq!(t.traverse_slice(&[1, 2]), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(&[1]), Lookup::Exists);
+    assert_eq!(t.traverse_slice(&[]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&[1, 2, 0, 0, 0, 0, 0, 0, 0]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&[1; 17]), Lookup::NoSuchPath);
+}
+
+#[test]
+fn test_deep_chain() {
+    // A single chain 1, 2, ..., 12 with values at depths 10 and 12, plus a branch hanging off
+    // depth 8 so the carry between the low and high match halves gets exercised on both sides.
+    let mut edges = BTreeSet::new();
+    for i in 0..12 {
+        edges.insert(Edge {
+            parent: if i == 0 { None } else { Some(i - 1) },
+            label: i as u8 + 1,
+            number: i,
+            has_value: i == 9 || i == 11,
+            has_branch: false,
+        });
+    }
+    edges.insert(Edge { parent: Some(7), label: 42, number: 12, has_value: false, has_branch: true });
+    let t = ByteTrie16::new(&edges);
+
+    let mut query = [0u8; 16];
+    for (i, q) in query.iter_mut().enumerate().take(12) {
+        *q = i as u8 + 1;
+    }
+    // `traverse_deep` wants the bytes past the length zeroed.
+    let deep = |query: &[u8; 16], len: usize| {
+        let mut padded = [0u8; 16];
+        padded[..len].copy_from_slice(&query[..len]);
+        t.traverse_deep(&padded, len)
+    };
+    assert_eq!(deep(&query, 10), Lookup::Value(0));
+    assert_eq!(deep(&query, 11), Lookup::Exists);
+    assert_eq!(deep(&query, 12), Lookup::Value(1));
+    assert_eq!(deep(&query, 13), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&query[..12]), Lookup::Value(1));
+
+    query[8] = 42;
+    assert_eq!(deep(&query, 9), Lookup::Branch(0));
+    assert_eq!(deep(&query, 10), Lookup::NoSuchPath);
+}
+
+#[test]
+fn test_depth_boundaries() {
+    // Construction counts depth in edges from the root, the same as query length, so a key of 8
+    // labels is the deepest `traverse` can reach (bit 7 of `matches7`) and one of 16 is the
+    // deepest `traverse_deep` can (bit 7 of the high half).
+    let eight: Vec<u8> = (1..=8).collect();
+    let t = ByteTrie16::from_keys(vec![eight.clone()]).unwrap();
+    let query: [u8; 8] = eight[..].try_into().unwrap();
+    assert_eq!(t.max_depth(), 8);
+    assert_eq!(t.traverse(&query, 8), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(&eight[..7]), Lookup::Exists);
+    assert_eq!(t.traverse(&query, 9), Lookup::NoSuchPath);
+    assert!(t.contains_key(&query, 8));
+    assert_eq!(t.longest_prefix_match(&query, 8), Some((0, 8)));
+    assert_eq!(t.prefix_len(&query, 8), 8);
+    assert_eq!(t.traverse_slice(&eight), Lookup::Value(0));
+    let mut deep_query = [0u8; 16];
+    deep_query[..8].copy_from_slice(&eight);
+    assert_eq!(t.traverse_deep(&deep_query, 8), Lookup::Value(0));
+
+    let sixteen: Vec<u8> = (1..=16).collect();
+    let t = ByteTrie16::from_keys(vec![sixteen[..8].to_vec(), sixteen[..9].to_vec(), sixteen.clone()]).unwrap();
+    let query: [u8; 16] = sixteen[..].try_into().unwrap();
+    assert_eq!(t.max_depth(), 16);
+    for (len, lookup) in &[(8, Lookup::Value(0)), (9, Lookup::Value(1)), (15, Lookup::Exists)] {
+        let mut padded = [0u8; 16];
+        padded[..*len].copy_from_slice(&query[..*len]);
+        assert_eq!(t.traverse_deep(&padded, *len), *lookup);
+    }
+    assert_eq!(t.traverse_deep(&query, 16), Lookup::Value(2));
+    assert_eq!(t.traverse_deep(&query, 17), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&sixteen), Lookup::Value(2));
+
+    let seventeen: Vec<u8> = (1..=17).collect();
+    assert_eq!(ByteTrie32::from_keys(vec![seventeen]).err(), Some(BuildError::DepthExceeded(17)));
+}
+
+#[test]
+fn test_map() {
+    let e = &[
+        Edge { parent: None,    label: 1, number: 0, has_value: false, has_branch: false },
+        Edge { parent: None,    label: 2, number: 1, has_value: true,  has_branch: false },
+        Edge { parent: Some(0), label: 3, number: 2, has_value: true,  has_branch: false },
+        Edge { parent: Some(0), label: 4, number: 3, has_value: false, has_branch: true  },
+    ];
+    let edges = e.iter().cloned().collect();
+    let m = ByteMap16::new(&edges, vec!["two", "one-three"]);
+    assert_eq!(m.get(&[2, 0, 0, 0, 0, 0, 0, 0], 1), Some(&"two"));
+    assert_eq!(m.get(&[1, 3, 0, 0, 0, 0, 0, 0], 2), Some(&"one-three"));
+    assert_eq!(m.get(&[1, 4, 0, 0, 0, 0, 0, 0], 2), None);
+    assert_eq!(m.get(&[1, 0, 0, 0, 0, 0, 0, 0], 1), None);
+    assert_eq!(m.traverse(&[1, 4, 0, 0, 0, 0, 0, 0], 2), Lookup::Branch(0));
+    let entries: Vec<(Vec<u8>, &&str)> = m.iter().collect();
+    assert_eq!(entries, vec![(vec![2], &"two"), (vec![1, 3], &"one-three")]);
+}
+
+#[test]
+fn test_trie_macro() {
+    let e = &[
+        Edge { parent: None,    label: 1, number: 0, has_value: false, has_branch: false },
+        Edge { parent: Some(0), label: 3, number: 1, has_value: true,  has_branch: false },
+        Edge { parent: Some(0), label: 4, number: 2, has_value: false, has_branch: true  },
+        Edge { parent: None,    label: 2, number: 3, has_value: true,  has_branch: true  },
+        Edge { parent: Some(3), label: 5, number: 4, has_value: false, has_branch: false },
+        Edge { parent: Some(4), label: 7, number: 5, has_value: true,  has_branch: false },
+    ];
+    let edges: BTreeSet<Edge> = e.iter().cloned().collect();
+    let built = crate::trie! {
+        1 => { 3 = value, 4 = branch },
+        2 = value + branch => { 5 => { 7 = value } },
+    };
+    assert_eq!(built, edges);
+
+    let words = crate::trie! { b'a' => { b'b' = value }, (b'a' + 2) = value };
+    let t = ByteTrie16::new(&words);
+    assert_eq!(t, ByteTrie16::from_keys(vec![b"ab".to_vec(), b"c".to_vec()]).unwrap());
+    assert_eq!(crate::trie! {}, BTreeSet::new());
+
+    let duplicate = crate::trie! { 1 = value, 1 = branch };
+    assert_eq!(ByteTrie16::try_new(&duplicate).err(), Some(BuildError::DuplicateSiblingLabel { parent: None, label: 1 }));
+}
+
+#[test]
+fn test_map_from_map() {
+    let mut words = HashMap::new();
+    words.insert(b"dog".to_vec(), 3);
+    words.insert(b"cat".to_vec(), 1);
+    words.insert(b"cats".to_vec(), 2);
+    let m = ByteMap16::from_map(words).unwrap();
+    assert_eq!(m.values(), &[1, 2, 3]);
+    assert_eq!(m.get(b"cats\0\0\0\0", 4), Some(&2));
+    assert_eq!(m.get(b"dog\0\0\0\0\0", 3), Some(&3));
+    assert_eq!(m.get(b"ca\0\0\0\0\0\0", 2), None);
+
+    // Owned values come back out in rank order, matching `traverse`.
+    let owned = ["b", "ab", "a"].iter().map(|k| (k.as_bytes().to_vec(), String::from(*k)));
+    let m = ByteMap16::from_map(owned).unwrap();
+    assert_eq!(m.traverse(b"ab\0\0\0\0\0\0", 2), Lookup::Value(1));
+    assert_eq!(m.into_values().collect::<Vec<String>>(), vec!["a", "ab", "b"]);
+
+    let m = ByteMap16::from_map(vec![(b"a".to_vec(), "first"), (b"a".to_vec(), "second")]).unwrap();
+    assert_eq!(m.iter().collect::<Vec<_>>(), vec![(b"a".to_vec(), &"second")]);
+    assert_eq!(ByteMap16::from_map(vec![(vec![], 0)]).err(), Some(BuildError::EmptyKey));
+    let many = (0..17u8).map(|i| (vec![i], i));
+    assert_eq!(ByteMap16::from_map(many).err(), Some(BuildError::TooManyNodes(17)));
+}
+
+#[test]
+fn test_linked() {
+    // "config." branches off to the section names, and "config.net." off to its settings.
+    let settings = LinkedTrie16::leaf(ByteTrie16::from_strs(["timeout", "retries"]).unwrap());
+    let sections = crate::trie! { b'n' => { b'e' => { b't' = value => { b'.' = branch } } }, b'u' => { b'i' = value } };
+    let sections = LinkedTrie16::new(ByteTrie16::new(&sections), vec![settings]);
+    let root = crate::trie! { b'c' => { b'o' => { b'n' => { b'f' => { b'i' => { b'g' => { b'.' = branch } } } } } } };
+    let root = LinkedTrie16::new(ByteTrie16::new(&root), vec![sections]);
+
+    let (trie, lookup) = root.traverse(b"config.net.retries").unwrap();
+    assert_eq!(lookup, Lookup::Value(0));
+    assert_eq!(trie, &root.branches()[0].branches()[0]);
+    assert_eq!(root.traverse(b"config.net.timeout").unwrap().1, Lookup::Value(1));
+    assert_eq!(root.traverse(b"config.net").unwrap().1, Lookup::Value(0));
+    assert_eq!(root.traverse(b"config.net.").unwrap().1, Lookup::Branch(0));
+    assert_eq!(root.traverse(b"config.ui").unwrap().1, Lookup::Value(1));
+    assert_eq!(root.traverse(b"config.").unwrap().1, Lookup::Branch(0));
+    assert_eq!(root.traverse(b"config.net.mtu"), None);
+    assert_eq!(root.traverse(b"config.ne"), None);
+    assert_eq!(root.traverse(b"conf"), None);
+    assert_eq!(root.traverse(b""), None);
+}
+
+#[test]
+fn test_arena() {
+    let mut arena = TrieArena16::new();
+    assert!(arena.is_empty());
+    let words = ByteTrie16::from_keys(vec![b"cat".to_vec(), b"dog".to_vec()]).unwrap();
+    let numbers = ByteTrie16::from_keys(vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]).unwrap();
+    assert_eq!(arena.push(words, vec!["cat", "dog"]), 0);
+    assert_eq!(arena.push(ByteTrie16::empty(), vec![]), 1);
+    assert_eq!(arena.push(numbers, vec!["one", "three", "two"]), 2);
+    assert_eq!(arena.len(), 3);
+
+    let q = |s: &[u8]| {
+        let mut query = [0u8; 8];
+        query[..s.len()].copy_from_slice(s);
+        query
+    };
+    assert_eq!(arena.get(0, &q(b"dog"), 3), Some(&"dog"));
+    assert_eq!(arena.get(2, &q(b"three"), 5), Some(&"three"));
+    assert_eq!(arena.get(2, &q(b"two"), 3), Some(&"two"));
+    assert_eq!(arena.get(2, &q(b"dog"), 3), None);
+    assert_eq!(arena.get(1, &q(b"dog"), 3), None);
+    assert_eq!(arena.values(1), &[] as &[&str]);
+    assert_eq!(arena.values(2), &["one", "three", "two"]);
+    assert_eq!(arena.trie(0), &words);
+}
+
+#[test]
+fn test_value_and_branch() {
+    let e = &[
+        Edge { parent: None,    label: 1, number: 0, has_value: true,  has_branch: true  },
+        Edge { parent: Some(0), label: 2, number: 1, has_value: false, has_branch: true  },
+        Edge { parent: Some(1), label: 3, number: 2, has_value: true,  has_branch: true  },
+        Edge { parent: Some(0), label: 4, number: 3, has_value: true,  has_branch: false },
+    ];
+    let edges = e.iter().cloned().collect();
+    let m = ByteMap16::new(&edges, vec!["one", "one-two-three", "one-four"]);
+    assert_eq!(m.traverse(&[1, 0, 0, 0, 0, 0, 0, 0], 1), Lookup::ValueAndBranch(0, 0));
+    assert_eq!(m.traverse(&[1, 2, 0, 0, 0, 0, 0, 0], 2), Lookup::Branch(1));
+    assert_eq!(m.traverse(&[1, 2, 3, 0, 0, 0, 0, 0], 3), Lookup::ValueAndBranch(1, 2));
+    assert_eq!(m.traverse(&[1, 4, 0, 0, 0, 0, 0, 0], 2), Lookup::Value(2));
+    assert_eq!(m.get(&[1, 2, 3, 0, 0, 0, 0, 0], 3), Some(&"one-two-three"));
+    assert!(m.trie().contains_key(&[1, 0, 0, 0, 0, 0, 0, 0], 1));
+    assert_eq!(m.trie().value_rank(&[1, 2, 3, 0, 0, 0, 0, 0], 3), Some(1));
+    assert_eq!(m.trie().value_rank(&[1, 2, 0, 0, 0, 0, 0, 0], 2), None);
+
+    let t = m.trie();
+    let query = [1, 2, 3, 0, 0, 0, 0, 0];
+    assert_eq!(t.traverse_with_precedence(&query, 3, Precedence::Value), Lookup::Value(1));
+    assert_eq!(t.traverse_with_precedence(&query, 3, Precedence::Branch), Lookup::Branch(2));
+    assert_eq!(t.traverse_with_precedence(&[1, 4, 0, 0, 0, 0, 0, 0], 2, Precedence::Branch), Lookup::Value(2));
+}
+
+#[test]
+fn test_flat() {
+    // A byte dispatch table: every lane a root, with every combination of flags.
+    // Labels go down as numbers go up, so lane order and label order disagree.
+    let edges: Vec<Edge> = (0..16)
+        .map(|i| {
+            let label = 0xF0 - i as u8 * 7;
+            Edge { parent: None, label, number: i, has_value: i % 2 == 0, has_branch: i % 3 == 0 }
+        })
+        .collect();
+    let t = ByteTrie16::new(&edges.iter().cloned().collect());
+    assert_eq!(t.len(), 16);
+    assert_eq!(t.max_depth(), 1);
+
+    let mut num_values = 0;
+    let mut num_branches = 0;
+    for e in &edges {
+        let expected = match (e.has_value, e.has_branch) {
+            (true, true) => Lookup::ValueAndBranch(num_values, num_branches),
+            (true, false) => Lookup::Value(num_values),
+            (false, true) => Lookup::Branch(num_branches),
+            (false, false) => Lookup::Exists,
+        };
+        num_values += e.has_value as u8;
+        num_branches += e.has_branch as u8;
+
+        assert_eq!(t.traverse(&[e.label, 0, 0, 0, 0, 0, 0, 0], 1), expected);
+        // Nothing hangs off a root, so going any further misses.
+        assert_eq!(t.traverse(&[e.label, e.label, 0, 0, 0, 0, 0, 0], 2), Lookup::NoSuchPath);
+    }
+    for byte in (0..=255).filter(|&b| !edges.iter().any(|e| e.label == b)) {
+        assert_eq!(t.traverse(&[byte, 0, 0, 0, 0, 0, 0, 0], 1), Lookup::NoSuchPath);
+    }
+}
+
+#[test]
+fn test_single_byte() {
+    // Every label is on a root and on a deeper node, so the single byte path has to skip the
+    // deeper ones.
+    let keys = vec![b"ab".to_vec(), b"ba".to_vec(), b"c".to_vec(), b"cab".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    for byte in 0..=255 {
+        let mut deep = [0u8; 16];
+        deep[0] = byte;
+        let lookup = t.traverse(&[byte, 0, 0, 0, 0, 0, 0, 0], 1);
+        assert_eq!(lookup, t.traverse_deep(&deep, 1));
+        let expected = match byte {
+            b'a' | b'b' => Lookup::Exists,
+            b'c' => Lookup::Value(2),
+            _ => Lookup::NoSuchPath,
+        };
+        assert_eq!(lookup, expected);
+    }
+}
+
+#[test]
+fn test_depth() {
+    let keys = vec![b"ab".to_vec(), b"abc".to_vec(), b"b".to_vec()];
+    let full = ByteTrie16::from_keys(keys.clone()).unwrap();
+    let t = ByteTrieDepth::<16, 3>::new(&full.iter_edges().collect());
+    assert_eq!(t.trie(), &full);
+    assert_eq!(t.traverse(b"ab\0", 2), Lookup::Value(0));
+    assert_eq!(t.traverse(b"abc", 3), Lookup::Value(1));
+    assert_eq!(t.traverse(b"b\0\0", 1), Lookup::Value(2));
+    assert_eq!(t.traverse(b"a\0\0", 1), Lookup::Exists);
+    assert_eq!(t.traverse(b"\0\0\0", 0), Lookup::NoSuchPath);
+    assert_eq!(t.traverse(b"abc", 4), Lookup::NoSuchPath);
+
+    let shallow = ByteTrieDepth::<16, 2>::try_new(&full.iter_edges().collect());
+    assert_eq!(shallow, Err(BuildError::DepthExceeded(3)));
+}
+
+#[test]
+fn test_fallback() {
+    let edge = |parent, label, number, has_value| Edge { parent, label, number, has_value, has_branch: false };
+
+    let small: BTreeSet<Edge> = vec![edge(None, b'a', 0, false), edge(Some(0), b'b', 1, true)].into_iter().collect();
+    let t = Trie16::build(&small).unwrap();
+    assert!(matches!(t, Trie16::Simd(_)));
+    assert_eq!(t.traverse(b"ab"), Lookup::Value(0));
+
+    // Too many edges and too deep for any `ByteTrie`.
+    let chain: BTreeSet<Edge> = (0..40usize).map(|i| edge(i.checked_sub(1), i as u8, i, i % 10 == 9)).collect();
+    let t = Trie16::build(&chain).unwrap();
+    assert!(matches!(t, Trie16::Fallback(_)));
+    let key: Vec<u8> = (0..40).collect();
+    assert_eq!(t.traverse(&key[..10]), Lookup::Value(0));
+    assert_eq!(t.traverse(&key[..11]), Lookup::Exists);
+    assert_eq!(t.traverse(&key), Lookup::Value(3));
+    assert_eq!(t.traverse(&key[1..]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse(b""), Lookup::NoSuchPath);
+
+    // Mistakes that aren't about size still fail.
+    let mut orphaned = chain.clone();
+    orphaned.insert(edge(Some(99), 0, 40, false));
+    assert_eq!(Trie16::build(&orphaned), Err(BuildError::OrphanEdge { number: 40, parent: 99 }));
+    let mut duplicate = chain;
+    duplicate.insert(edge(None, 0, 40, false));
+    assert_eq!(Trie16::build(&duplicate), Err(BuildError::DuplicateSiblingLabel { parent: None, label: 0 }));
+
+    let mut values: BTreeSet<Edge> = (0..256usize).map(|i| edge(None, i as u8, i, true)).collect();
+    values.insert(edge(Some(0), 0, 256, true));
+    assert_eq!(Trie16::build(&values), Err(BuildError::TooManyRanks(257)));
+}
+
+#[test]
+fn test_builder() {
+    // Same shape as `test_tree`.
+    let mut b = TrieBuilder::new();
+    let n1 = b.add_root(1).unwrap();
+    let n2 = b.add_root(2).unwrap();
+    let n3 = b.add_child(n1, 3).unwrap();
+    let n4 = b.add_child(n1, 4).unwrap();
+    let n5 = b.add_child(n2, 5).unwrap();
+    let n7 = b.add_child(n5, 7).unwrap();
+    b.set_value(n3);
+    b.set_value(n4);
+    b.set_value(n7);
+    b.set_branch(n5);
+    let t = b.build();
+    assert_eq!(t.traverse_slice(&[1, 3]), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(&[1, 4]), Lookup::Value(1));
+    assert_eq!(t.traverse_slice(&[2, 5]), Lookup::Branch(0));
+    assert_eq!(t.traverse_slice(&[2, 5, 7]), Lookup::Value(2));
+    assert_eq!(t.traverse_slice(&[1]), Lookup::Exists);
+
+    // Values added out of order still come back in rank order.
+    let mut b = TrieBuilder::new();
+    let g = b.add_root(b'g').unwrap();
+    let ge = b.add_child(g, b'e').unwrap();
+    let get = b.add_child(ge, b't').unwrap();
+    let p = b.add_root(b'p').unwrap();
+    let pu = b.add_child(p, b'u').unwrap();
+    let put = b.add_child(pu, b't').unwrap();
+    let gets = b.add_child(get, b's').unwrap();
+    for node in [put, gets, get].iter() {
+        b.set_value(*node);
+    }
+    b.set_branch(ge);
+    let (t, names) = b.build_with_names();
+    assert_eq!(names, vec!["get", "put", "gets"]);
+    for (rank, name) in names.iter().enumerate() {
+        assert_eq!(t.traverse_slice(name.as_bytes()), Lookup::Value(rank as u8));
+    }
+
+    let mut b = TrieBuilder::new();
+    for label in 0..16 {
+        b.add_root(label).unwrap();
+    }
+    assert_eq!(b.add_root(16), Err(BuildError::TooManyNodes(17)));
+
+    // Mistakes come back from `add_*`, and leave the builder as it was.
+    let mut b = TrieBuilder::new();
+    let a = b.add_root(b'a').unwrap();
+    let ab = b.add_child(a, b'b').unwrap();
+    assert_eq!(b.add_root(b'a'), Err(BuildError::DuplicateSiblingLabel { parent: None, label: b'a' }));
+    assert_eq!(b.add_child(a, b'b'), Err(BuildError::DuplicateSiblingLabel { parent: Some(0), label: b'b' }));
+    let mut other = TrieBuilder::new();
+    let foreign = (0..5).map(|label| other.add_root(label).unwrap()).last().unwrap();
+    assert_eq!(b.add_child(foreign, b'c'), Err(BuildError::OrphanEdge { number: 2, parent: 4 }));
+    b.set_value(ab);
+    assert_eq!(b.build(), ByteTrie16::from_strs(["ab"]).unwrap());
+}
+
+#[test]
+#[should_panic(expected = "node 4 isn't one of this builder's 1")]
+fn test_builder_foreign_node() {
+    let mut other = TrieBuilder::new();
+    let foreign = (0..5).map(|label| other.add_root(label).unwrap()).last().unwrap();
+    let mut b = TrieBuilder::new();
+    b.add_root(b'a').unwrap();
+    b.set_value(foreign);
+}
+
+#[test]
+fn test_from_keys() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"ca".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    assert_eq!(t.traverse_slice(b"ca"), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(b"car"), Lookup::Value(1));
+    assert_eq!(t.traverse_slice(b"cat"), Lookup::Value(2));
+    assert_eq!(t.traverse_slice(b"dog"), Lookup::Value(3));
+    assert_eq!(t.traverse_slice(b"c"), Lookup::Exists);
+    assert_eq!(t.traverse_slice(b"do"), Lookup::Exists);
+    assert_eq!(t.traverse_slice(b"cab"), Lookup::NoSuchPath);
+
+    assert_eq!(ByteTrie16::from_keys(vec![vec![]]).err(), Some(BuildError::EmptyKey));
+    assert_eq!(ByteTrie16::from_keys(vec![vec![1; 17]]).err(), Some(BuildError::DepthExceeded(17)));
+    let wide = (0..17).map(|i| vec![i]);
+    assert_eq!(ByteTrie16::from_keys(wide).err(), Some(BuildError::TooManyNodes(17)));
+}
+
+#[test]
+fn test_zero_label() {
+    // Unused lanes have label 0 too, so a query of zeros lines up with every one of them.
+    let edge = |parent, label, number, has_value| Edge { parent, label, number, has_value, has_branch: false };
+    let edges = vec![edge(None, 0, 0, true), edge(Some(0), 0, 1, false), edge(Some(1), 0, 2, true)];
+    let t = ByteTrie16::new(&edges.into_iter().collect());
+    assert_eq!(t.traverse_slice(&[0]), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(&[0, 0]), Lookup::Exists);
+    assert_eq!(t.traverse_slice(&[0, 0, 0]), Lookup::Value(1));
+    for query_len in 4..=16 {
+        assert_eq!(t.traverse_slice(&vec![0; query_len]), Lookup::NoSuchPath);
+    }
+    assert!(t.contains_prefix(&[0; 8], 3));
+    assert!(!t.contains_prefix(&[0; 8], 4));
+    assert_eq!(t.longest_prefix_match(&[0; 8], 8), Some((1, 3)));
+
+    let t = ByteTrie16::from_keys(vec![b"a".to_vec(), vec![b'a', 0]]).unwrap();
+    assert_eq!(t.traverse_slice(&[b'a', 0]), Lookup::Value(1));
+    assert_eq!(t.traverse_slice(&[b'a', 0, 0]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&[0]), Lookup::NoSuchPath);
+    assert_eq!(ByteTrie16::empty().longest_prefix_match(&[0; 8], 8), None);
+}
+
+#[test]
+fn test_from_keys_capped() {
+    let keys = |keys: &[(&str, u32)]| keys.iter().map(|&(k, p)| (k.as_bytes().to_vec(), p)).collect::<Vec<_>>();
+
+    // Fits already, so nothing goes.
+    let (t, dropped) = ByteTrie16::from_keys_capped(keys(&[("cat", 1), ("cats", 0)])).unwrap();
+    assert_eq!(t, ByteTrie16::from_strs(["cat", "cats"]).unwrap());
+    assert!(dropped.is_empty());
+
+    // 20 edges. "ca" would free nothing while "cat" runs through it, so it stays despite the lowest
+    // priority, and "card" only frees its "rd", so it takes "dogs" too to get down to 16.
+    let input = keys(&[("cat", 9), ("card", 1), ("ca", 0), ("dogs", 2), ("horse", 5), ("house", 5), ("bat", 3)]);
+    let (t, dropped) = ByteTrie16::from_keys_capped(input).unwrap();
+    assert_eq!(dropped, vec![b"card".to_vec(), b"dogs".to_vec()]);
+    assert_eq!(t, ByteTrie16::from_strs(["bat", "ca", "cat", "horse", "house"]).unwrap());
+    assert_eq!(t.len(), 14);
+
+    // 23 edges. "ab" waits until "abcdefgh" goes, and then it's next, since it's still the lowest.
+    let input = keys(&[("ab", 0), ("abcdefgh", 1), ("qrstuvwxyzABCDE", 5)]);
+    let (t, dropped) = ByteTrie16::from_keys_capped(input).unwrap();
+    assert_eq!(dropped, vec![b"abcdefgh".to_vec(), b"ab".to_vec()]);
+    assert_eq!(t, ByteTrie16::from_strs(["qrstuvwxyzABCDE"]).unwrap());
+
+    // Ties drop the larger key first, and a repeated key keeps its higher priority.
+    let input = keys(&[("abcdefgh", 1), ("bcdefghi", 1), ("cdefghij", 0), ("cdefghij", 1), ("defghijk", 2)]);
+    let (_, dropped) = ByteTrie16::from_keys_capped(input).unwrap();
+    assert_eq!(dropped, vec![b"cdefghij".to_vec(), b"bcdefghi".to_vec()]);
+
+    assert_eq!(ByteTrie16::from_keys_capped(keys(&[("", 1)])).err(), Some(BuildError::EmptyKey));
+}
+
+#[test]
+fn test_strs() {
+    let t = ByteTrie16::from_strs(["été", "ét"]).unwrap();
+    assert_eq!(t.traverse_str("ét"), Lookup::Value(0));
+    assert_eq!(t.traverse_str("été"), Lookup::Value(1));
+    assert_eq!(t.traverse_str("é"), Lookup::Exists);
+    assert_eq!(t.traverse_str(""), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_str("ét\u{301}"), Lookup::NoSuchPath);
+
+    // Eight characters, but sixteen bytes, which still fits; one more character doesn't.
+    let t = ByteTrie32::from_strs(vec![String::from("éééééééé")]).unwrap();
+    assert_eq!(t.traverse_str("éééééééé"), Lookup::Value(0));
+    assert_eq!(ByteTrie32::from_strs(["ééééééééé"]).err(), Some(BuildError::DepthExceeded(18)));
+}
+
+#[test]
+fn test_traverse_iter() {
+    let t = ByteTrie16::from_keys(vec![b"get".to_vec(), b"gets".to_vec(), b"patch".to_vec()]).unwrap();
+    assert_eq!(t.traverse_iter(b"gets".iter().copied()), Lookup::Value(1));
+    assert_eq!(t.traverse_iter(vec![b'p', b'a']), Lookup::Exists);
+    assert_eq!(t.traverse_iter(b"patch".iter().copied()), Lookup::Value(2));
+    assert_eq!(t.traverse_iter(b"patches".iter().copied()), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_iter(core::iter::empty()), Lookup::NoSuchPath);
+
+    // Never pulls more than one byte past the deepest path, even from an endless source.
+    let mut pulled = 0;
+    let endless = core::iter::repeat(b'g').inspect(|_| pulled += 1);
+    assert_eq!(t.traverse_iter(endless), Lookup::NoSuchPath);
+    assert_eq!(pulled, 6);
+
+    let deep: Vec<u8> = (1..=12).collect();
+    let t = ByteTrie16::from_keys(vec![deep.clone()]).unwrap();
+    assert_eq!(t.traverse_iter(deep.iter().copied()), Lookup::Value(0));
+}
+
+#[test]
+fn test_dual() {
+    // The same labels on both sides, so a lookup that leaked into the other half would show up.
+    let low = crate::trie! { 1 => { 2 = value, 3 = value + branch }, 4 = branch };
+    let high = crate::trie! { 1 = value => { 3 = value => { 5 = value } }, 9 = value };
+    let dual = DualTrie8::new(&low, &high);
+    let tries = [ByteTrie16::new(&low), ByteTrie16::new(&high)];
+    for (which, trie) in tries.iter().enumerate() {
+        for query_len in 1..=4 {
+            for first in &[1, 4, 9] {
+                for rest in &[2, 3, 5] {
+                    let mut query = [0u8; 8];
+                    query[0] = *first;
+                    query[1..query_len].iter_mut().enumerate().for_each(|(i, b)| *b = [*rest, 5, 5][i]);
+                    assert_eq!(dual.traverse(which, &query, query_len), trie.traverse(&query, query_len));
+                }
+            }
+        }
+    }
+    assert_eq!(dual.traverse(1, &[1, 3, 5, 0, 0, 0, 0, 0], 3), Lookup::Value(2));
+    assert_eq!(dual.traverse(0, &[1, 3, 5, 0, 0, 0, 0, 0], 3), Lookup::NoSuchPath);
+
+    let nine = crate::trie! { 1, 2, 3, 4, 5, 6, 7, 8, 9 };
+    assert_eq!(DualTrie8::try_new(&low, &nine).err(), Some(BuildError::TooManyNodes(9)));
+}
+
+#[test]
+fn test_edge_matches() {
+    let t = ByteTrie16::from_keys(vec![b"ab".to_vec(), b"ba".to_vec()]).unwrap();
+    let matches = t.edge_matches(b"aba\0\0\0\0\0");
+    for (lane, &bits) in matches.iter().enumerate() {
+        let expected = match t.node_label(lane as u8) {
+            Some(b'a') => 0b0000_0101,
+            Some(b'b') => 0b0000_0010,
+            Some(_) => unreachable!(),
+            // Unused lanes are labeled 0, like the padding.
+            None => 0b1111_1000,
+        };
+        assert_eq!(bits, expected, "lane {}", lane);
+    }
+}
+
+#[test]
+fn test_value_or_ancestor() {
+    let t = ByteTrie16::new(&crate::trie! {
+        b'a' = value => { b'b' => { b'c' => { b'd' = value } }, b'x' = value => { b'y' } },
+        b'q' => { b'r' },
+    });
+    let q = |s: &[u8]| {
+        let mut query = [0u8; 8];
+        query[..s.len()].copy_from_slice(s);
+        query
+    };
+    assert_eq!(t.value_or_ancestor(&q(b"abcd"), 4), Some(1));
+    assert_eq!(t.value_or_ancestor(&q(b"abc"), 3), Some(0));
+    assert_eq!(t.value_or_ancestor(&q(b"axy"), 3), Some(2));
+    assert_eq!(t.value_or_ancestor(&q(b"a"), 1), Some(0));
+    // Not paths in the trie, even though they start with one that has a value.
+    assert_eq!(t.value_or_ancestor(&q(b"abz"), 3), None);
+    assert_eq!(t.value_or_ancestor(&q(b"abcde"), 5), None);
+    assert_eq!(t.value_or_ancestor(&q(b"qr"), 2), None);
+    assert_eq!(t.value_or_ancestor(&q(b""), 0), None);
+}
+
+#[test]
+fn test_first_byte_mask() {
+    let t = ByteTrie16::from_strs(["get", "put", "post"]).unwrap();
+    let mask = t.first_byte_mask();
+    assert_eq!((0..=255u8).filter(|&b| mask[b as usize]).collect::<Vec<_>>(), b"gp".to_vec());
+    assert!(ByteTrie16::empty().first_byte_mask().iter().all(|&m| !m));
+
+    // Only look up where a key can start.
+    let buffer = b"a get, then a post";
+    let found: Vec<(usize, Lookup)> = (0..buffer.len())
+        .filter(|&i| mask[buffer[i] as usize])
+        .flat_map(|i| (1..=4).filter_map(move |len| buffer.get(i..i + len)).map(move |key| (i, t.traverse_slice(key))))
+        .filter(|(_, lookup)| !matches!(lookup, Lookup::NoSuchPath | Lookup::Exists))
+        .collect();
+    assert_eq!(found, vec![(2, Lookup::Value(0)), (14, Lookup::Value(1))]);
+}
+
+#[test]
+fn test_stats() {
+    let t = ByteTrie16::new(&crate::trie! { b'a' = branch => { b'b' = value, b'a' = value + branch }, b'b' => { b'c' } });
+    let stats = TrieStats { len: 5, max_depth: 2, num_values: 2, num_branches: 2, num_labels: 3 };
+    assert_eq!(t.stats(), stats);
+    assert_eq!(ByteTrie32::empty().stats(), TrieStats::default());
+}
+
+#[test]
+fn test_rebuild_into() {
+    let mut rng = IsaacRng::seed_from_u64(95);
+    let mut scratch = BuildScratch::new();
+    let mut t = ByteTrie16::empty();
+    for _ in 0..20 {
+        let slow = TestTree::generate(&mut rng, 16, 8, 1.5);
+        t.rebuild_into(&slow.edges, &mut scratch).unwrap();
+        assert_eq!(t, ByteTrie16::new(&slow.edges));
+    }
+
+    // Errors are the same as `try_new`'s, and leave the trie alone.
+    let before = t;
+    let bad = [
+        crate::trie! { 1, 1 },
+        (0..17).map(|i| Edge { parent: None, label: i as u8, number: i, has_value: true, has_branch: false }).collect(),
+        IntoIterator::into_iter([Edge { parent: Some(3), label: 1, number: 0, has_value: true, has_branch: false }])
+            .collect(),
+    ];
+    for edges in &bad {
+        assert_eq!(t.rebuild_into(edges, &mut scratch).err(), ByteTrie16::try_new(edges).err());
+        assert!(ByteTrie16::try_new(edges).is_err());
+        assert_eq!(t, before);
+    }
+}
+
+#[test]
+fn test_traverse_gather() {
+    let tries = [
+        ByteTrie16::from_strs(["get", "gets"]).unwrap(),
+        ByteTrie16::empty(),
+        ByteTrie16::from_strs(["ge", "put"]).unwrap(),
+        ByteTrie16::from_strs(["g"]).unwrap(),
+    ];
+    for key in ["g", "ge", "get", "gets", "put", "x", "getsx"] {
+        let mut query = [0u8; 8];
+        query[..key.len()].copy_from_slice(key.as_bytes());
+        let lookups = ByteTrie16::traverse_gather(&tries, &query, key.len());
+        for (trie, lookup) in tries.iter().zip(&lookups) {
+            assert_eq!(*lookup, trie.traverse(&query, key.len()), "{}", key);
+        }
+    }
+    let lookups = ByteTrie16::traverse_gather(&tries, b"ge\0\0\0\0\0\0", 2);
+    assert_eq!(lookups, [Lookup::Exists, Lookup::NoSuchPath, Lookup::Value(0), Lookup::NoSuchPath]);
+}
+
+#[test]
+fn test_too_long() {
+    let t = ByteTrie16::from_keys(vec![b"get".to_vec(), b"gets".to_vec()]).unwrap();
+    assert_eq!(t.max_depth(), 4);
+    assert_eq!(t.traverse(b"gets\0\0\0\0", 4), Lookup::Value(1));
+    assert_eq!(t.traverse(b"getsx\0\0\0", 5), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(b"getsxxxxxxxx"), Lookup::NoSuchPath);
+    let mut out = vec![Lookup::NoSuchPath, Lookup::NoSuchPath];
+    t.traverse_batch(&[(*b"get\0\0\0\0\0", 3), (*b"getsxxxx", 8)], &mut out);
+    assert_eq!(out, vec![Lookup::Value(0), Lookup::NoSuchPath]);
+    assert_eq!(ByteTrie16::empty().traverse(b"g\0\0\0\0\0\0\0", 1), Lookup::NoSuchPath);
+}
+
+#[test]
+fn test_full_lanes() {
+    check_full_lanes::<16>();
+    check_full_lanes::<32>();
+    check_full_lanes::<64>();
+}
+
+// Every lane labeled 0 except the roots, as deep as the lanes allow, so queries of zeros line up
+// with sentinels and real lanes alike. There are at least two roots, and enough to keep the paths
+// within 16 edges. Checked with every lane used and with the last one unused.
+fn check_full_lanes<const LANES: usize>()
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    let num_roots = (LANES / 16).max(2);
+    for num_edges in &[LANES - 1, LANES] {
+        let edges: BTreeSet<Edge> = (0..*num_edges)
+            .map(|i| Edge {
+                parent: i.checked_sub(num_roots),
+                label: if i < num_roots { i as u8 } else { 0 },
+                number: i,
+                has_value: i % 3 == 0,
+                has_branch: i % 4 == 1,
+            })
+            .collect();
+        let slow = TestTree { edges };
+        let fast = ByteTrie::<LANES>::new(&slow.edges);
+        assert_eq!(fast.len(), *num_edges);
+        for root in 0..=num_roots as u8 {
+            let mut query = [0u8; 16];
+            query[0] = root;
+            for query_len in 1..=16 {
+                check_query(&slow, &fast, &query, query_len);
+            }
+        }
+    }
+}
+
+// Every lane of a 32 lane trie can be a parent, right up to the last one the pointer can name.
+#[test]
+fn test_parent_pointers_32() {
+    let edges: BTreeSet<Edge> = (0..32)
+        .map(|i| Edge {
+            parent: if i == 0 { None } else { Some((i - 1) / 2) },
+            label: (i % 2) as u8,
+            number: i,
+            has_value: true,
+            has_branch: false,
+        })
+        .collect();
+    let t = ByteTrie32::new(&edges);
+    assert_eq!(t.validate(), Ok(()));
+    assert_eq!(t.node_parent(0), None);
+    for i in 1..32u8 {
+        assert_eq!(t.node_parent(i), Some((i - 1) / 2));
+    }
+    let round_trip: BTreeSet<Edge> = t.iter_edges().collect();
+    assert_eq!(round_trip, edges);
+
+    // The deepest node is lane 31, five edges down.
+    let mut lane = 31;
+    let mut path = vec![];
+    while let Some(parent) = t.node_parent(lane) {
+        path.push(t.node_label(lane).unwrap());
+        lane = parent;
+    }
+    path.push(t.node_label(0).unwrap());
+    path.reverse();
+    assert_eq!(t.traverse_slice(&path), Lookup::Value(31));
+}
+
+// The same heap numbered backwards from lane 63, so every parent pointer is at least 32 and needs
+// the sixth bit that only 64 lane nodes have.
+#[test]
+fn test_parent_pointers_64() {
+    let parent_of = |i: usize| 63 - (62 - i) / 2;
+    let edges: BTreeSet<Edge> = (0..64)
+        .map(|i| Edge {
+            parent: if i == 63 { None } else { Some(parent_of(i)) },
+            label: (i % 2) as u8,
+            number: i,
+            has_value: true,
+            has_branch: false,
+        })
+        .collect();
+    let t = ByteTrie64::new(&edges);
+    assert_eq!(t.validate(), Ok(()));
+    assert_eq!(t.node_parent(63), None);
+    for i in 0..63u8 {
+        assert_eq!(t.node_parent(i), Some(parent_of(i as usize) as u8));
+    }
+    let round_trip: BTreeSet<Edge> = t.iter_edges().collect();
+    assert_eq!(round_trip, edges);
+    assert_eq!(ByteTrie64::from_bytes_checked(t.to_bytes()), Ok(t));
+    let (edges, nodes) = t.packed();
+    assert_eq!(ByteTrie64::from_packed_const(edges, nodes), t);
+    assert_eq!(ByteTrie64::from_packed(edges, nodes), t);
+
+    // The deepest node is lane 0, six edges down.
+    let mut lane = 0;
+    let mut path = vec![];
+    while let Some(parent) = t.node_parent(lane) {
+        path.push(t.node_label(lane).unwrap());
+        lane = parent;
+    }
+    path.push(t.node_label(63).unwrap());
+    path.reverse();
+    assert_eq!(path.len(), 7);
+    assert_eq!(t.traverse_slice(&path), Lookup::Value(0));
+    assert_eq!(t.max_depth(), 7);
+}
+
+#[test]
+fn test_node_bits() {
+    use super::{parent_word, word_parent, BRANCH_BIT, PARENT_BITS, PARENT_MASK, ROOT_BIT, SENTINEL_NODE, VALUE_BIT};
+
+    // Every bit belongs to exactly one part.
+    assert_eq!(ROOT_BIT | VALUE_BIT | BRANCH_BIT | PARENT_MASK, 0xFF);
+    assert_eq!(ROOT_BIT & (VALUE_BIT | BRANCH_BIT | PARENT_MASK), 0);
+    assert_eq!(VALUE_BIT & (BRANCH_BIT | PARENT_MASK), 0);
+    assert_eq!(BRANCH_BIT & PARENT_MASK, 0);
+    assert_eq!(PARENT_MASK.count_ones(), PARENT_BITS);
+    assert_eq!(1usize << PARENT_BITS, 32);
+
+    // The sentinel is a root with no value, no branch, and an all ones parent pointer, which no
+    // real root has.
+    assert_eq!(SENTINEL_NODE, 0b1001_1111);
+    assert_eq!(SENTINEL_NODE & ROOT_BIT, ROOT_BIT);
+    assert_eq!(SENTINEL_NODE & VALUE_BIT, 0);
+    assert_eq!(SENTINEL_NODE & BRANCH_BIT, 0);
+    assert_eq!(SENTINEL_NODE & PARENT_MASK, PARENT_MASK);
+
+    // And the builder lays nodes out the same way.
+    let t = ByteTrie16::from_keys(vec![b"ab".to_vec()]).unwrap();
+    let (_, nodes) = t.packed();
+    assert_eq!(nodes[0], ROOT_BIT);
+    assert_eq!(nodes[1], VALUE_BIT);
+    assert!(nodes[2..].iter().all(|&n| n == SENTINEL_NODE));
+
+    // 64 lane nodes are the same byte, with the rest of the parent pointer in the byte above.
+    for parent in 0..64 {
+        let word = parent_word(parent);
+        assert_eq!(word_parent(word), parent);
+        assert_eq!(word_parent(word | u16::from(ROOT_BIT | VALUE_BIT | BRANCH_BIT)), parent);
+        assert_eq!(word as u8 & (ROOT_BIT | VALUE_BIT | BRANCH_BIT), 0);
+    }
+    assert_eq!(parent_word(33), 0b1_0000_0001);
+    let t = ByteTrie64::from_keys(vec![b"ab".to_vec()]).unwrap();
+    let (_, nodes) = t.packed();
+    assert_eq!(nodes[0], u16::from(ROOT_BIT));
+    assert_eq!(nodes[1], u16::from(VALUE_BIT));
+    assert!(nodes[2..].iter().all(|&n| n == u16::from(SENTINEL_NODE)));
+}
+
+#[test]
+fn test_empty() {
+    let t = ByteTrie16::empty();
+    assert_eq!(t, ByteTrie16::default());
+    assert_eq!(t, ByteTrie16::new(&BTreeSet::new()));
+    assert!(t.is_empty());
+    for query_len in 1..=8 {
+        assert_eq!(t.traverse(&[0; 8], query_len), Lookup::NoSuchPath);
+        let mut query = [0u8; 8];
+        query[..query_len].fill(0xFF);
+        assert_eq!(t.traverse(&query, query_len), Lookup::NoSuchPath);
+    }
+    assert_eq!(ByteTrie32::default().traverse_slice(&[0]), Lookup::NoSuchPath);
+}
+
+#[test]
+fn test_merge() {
+    let a = ByteTrie16::from_keys(vec![b"cat".to_vec(), b"car".to_vec()]).unwrap();
+    let b = ByteTrie16::from_keys(vec![b"ca".to_vec(), b"dog".to_vec(), b"cat".to_vec()]).unwrap();
+    let all = vec![b"ca".to_vec(), b"car".to_vec(), b"cat".to_vec(), b"dog".to_vec()];
+    assert_eq!(ByteTrie16::merge(&a, &b), ByteTrie16::from_keys(all));
+    assert_eq!(ByteTrie16::merge(&a, &a), Ok(a));
+
+    let edge = |has_value, has_branch| Edge { parent: None, label: 1, number: 0, has_value, has_branch };
+    let value = ByteTrie16::new(&vec![edge(true, false)].into_iter().collect());
+    let branch = ByteTrie16::new(&vec![edge(false, true)].into_iter().collect());
+    let both = ByteTrie16::merge(&value, &branch).unwrap();
+    assert_eq!(both.traverse_slice(&[1]), Lookup::ValueAndBranch(0, 0));
+
+    let a = ByteTrie16::from_keys(vec![b"abcdefghi".to_vec()]).unwrap();
+    let b = ByteTrie16::from_keys(vec![b"zyxwvutsr".to_vec()]).unwrap();
+    assert_eq!(ByteTrie16::merge(&a, &b), Err(BuildError::TooManyNodes(18)));
+    let a = ByteTrie32::from_keys(vec![b"abcdefghi".to_vec()]).unwrap();
+    let b = ByteTrie32::from_keys(vec![b"zyxwvutsr".to_vec()]).unwrap();
+    assert_eq!(ByteTrie32::merge(&a, &b).unwrap().len(), 18);
+}
+
+#[test]
+fn test_with_key() {
+    let keys = |keys: &[&[u8]]| ByteTrie16::from_keys(keys.iter().map(|k| k.to_vec())).unwrap();
+    let t = keys(&[b"cat"]).with_key(b"dog", true, false).unwrap();
+    assert_eq!(t.traverse_slice(b"cat"), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(b"dog"), Lookup::Value(1));
+    assert_eq!(t, keys(&[b"cat", b"dog"]));
+
+    // Only the missing suffix gets new edges.
+    let t = t.with_key(b"car", true, false).unwrap();
+    assert_eq!(t.len(), 7);
+    assert_eq!(t, keys(&[b"car", b"cat", b"dog"]));
+    let t = t.with_key(b"ca", false, true).unwrap();
+    assert_eq!(t.len(), 7);
+    assert_eq!(t.traverse_slice(b"ca"), Lookup::Branch(0));
+    assert_eq!(t.with_key(b"ca", true, false).unwrap().traverse_slice(b"ca"), Lookup::ValueAndBranch(0, 0));
+    assert_eq!(t.with_key(b"cat", true, false), Ok(t));
+
+    assert_eq!(t.with_key(b"", true, false), Err(BuildError::EmptyKey));
+    assert_eq!(t.with_key(&[1; 17], true, false), Err(BuildError::DepthExceeded(17)));
+    assert_eq!(t.with_key(b"elephant", true, false).unwrap().len(), 15);
+    assert_eq!(t.with_key(b"elephantine", true, false), Err(BuildError::TooManyNodes(18)));
+}
+
+#[test]
+fn test_without_key() {
+    let keys = |keys: &[&[u8]]| ByteTrie16::from_keys(keys.iter().map(|k| k.to_vec())).unwrap();
+    let t = keys(&[b"ca", b"car", b"cart", b"cat", b"dog"]);
+
+    assert_eq!(t.without_key(b"cart"), keys(&[b"ca", b"car", b"cat", b"dog"]));
+    // Only the part of the path nothing else needs goes away.
+    assert_eq!(t.without_key(b"dog"), keys(&[b"ca", b"car", b"cart", b"cat"]));
+    // A node with children keeps its place on their path.
+    assert_eq!(t.without_key(b"car").traverse_slice(b"car"), Lookup::Exists);
+    assert_eq!(t.without_key(b"car").traverse_slice(b"cart"), Lookup::Value(1));
+    assert_eq!(t.without_key(b"car").len(), t.len());
+    assert_eq!(t.without_key(b"car").without_key(b"cart"), keys(&[b"ca", b"cat", b"dog"]));
+
+    // Not keys, so nothing changes.
+    for &missing in &[&b"c"[..], b"do", b"cars", b"x", b""] {
+        assert_eq!(t.without_key(missing), t);
+    }
+
+    let single = keys(&[b"abc"]);
+    assert!(single.without_key(b"abc").is_empty());
+
+    // A value on a node with a branch leaves the branch.
+    let edge = Edge { parent: None, label: 1, number: 0, has_value: true, has_branch: true };
+    let t = ByteTrie16::new(&vec![edge].into_iter().collect());
+    assert_eq!(t.without_key(&[1]).traverse_slice(&[1]), Lookup::Branch(0));
+}
+
+#[test]
+fn test_try_new() {
+    let edge = |parent, number| Edge { parent, label: number as u8, number, has_value: true, has_branch: false };
+    let try_new = |edges: &[Edge]| ByteTrie16::try_new(&edges.iter().cloned().collect()).err();
+
+    assert_eq!(try_new(&[edge(None, 0), edge(Some(0), 1)]), None);
+    let wide: Vec<_> = (0..17).map(|i| edge(None, i)).collect();
+    assert_eq!(try_new(&wide), Some(BuildError::TooManyNodes(17)));
+    assert_eq!(try_new(&[edge(None, 20)]), Some(BuildError::TooManyNodes(21)));
+    assert_eq!(try_new(&[edge(None, 0), edge(Some(16), 1)]), Some(BuildError::ParentPointerOverflow(16)));
+    let mut dup = edge(None, 0);
+    dup.label = 9;
+    assert_eq!(try_new(&[edge(None, 0), dup]), Some(BuildError::DuplicateNumber(0)));
+    let mut twin = edge(Some(0), 2);
+    twin.label = 1;
+    let err = BuildError::DuplicateSiblingLabel { parent: Some(0), label: 1 };
+    assert_eq!(try_new(&[edge(None, 0), edge(Some(0), 1), twin]), Some(err));
+    let mut twin = edge(None, 1);
+    twin.label = 0;
+    let err = BuildError::DuplicateSiblingLabel { parent: None, label: 0 };
+    assert_eq!(try_new(&[edge(None, 0), twin]), Some(err));
+
+    let err = BuildError::OrphanEdge { number: 1, parent: 5 };
+    assert_eq!(try_new(&[edge(None, 0), edge(Some(5), 1)]), Some(err));
+    // 1 and 2 are each other's parents, so neither hangs off the root.
+    let err = BuildError::OrphanEdge { number: 1, parent: 2 };
+    assert_eq!(try_new(&[edge(None, 0), edge(Some(2), 1), edge(Some(1), 2)]), Some(err));
+
+    let mut chain: Vec<_> = (0..16usize).map(|i| edge(i.checked_sub(1), i)).collect();
+    assert_eq!(try_new(&chain), None);
+    chain.push(edge(Some(15), 16));
+    assert_eq!(ByteTrie32::try_new(&chain.iter().cloned().collect()).err(), Some(BuildError::DepthExceeded(17)));
+}
+
+#[test]
+fn test_with_capacity_limit() {
+    let edge = |number| Edge { parent: None, label: number as u8, number, has_value: true, has_branch: false };
+    let edges: BTreeSet<_> = (0..8).map(edge).collect();
+    assert_eq!(ByteTrie16::with_capacity_limit(&edges, 8), ByteTrie16::try_new(&edges));
+    assert_eq!(ByteTrie16::with_capacity_limit(&edges, 7), Err(BuildError::TooManyNodes(8)));
+    // Fewer edges than the limit, but one of them is numbered past it.
+    let sparse: BTreeSet<_> = vec![edge(0), edge(9)].into_iter().collect();
+    assert_eq!(ByteTrie16::with_capacity_limit(&sparse, 8), Err(BuildError::TooManyNodes(10)));
+    assert!(ByteTrie16::with_capacity_limit(&BTreeSet::new(), 0).unwrap().is_empty());
+    assert!(std::panic::catch_unwind(|| ByteTrie16::with_capacity_limit(&edges, 17)).is_err());
+}
+
+#[test]
+fn test_from_edges_auto() {
+    let t = ByteTrie16::from_edges_auto(&[
+        (None, b'c', false, false),
+        (Some(0), b'a', true, false),
+        (Some(1), b't', true, false),
+        (None, b'd', false, true),
+    ]);
+    assert_eq!(t, ByteTrie16::from_keys(vec![b"ca".to_vec(), b"cat".to_vec()]).unwrap().with_key(b"d", false, true));
+
+    let err = BuildError::ParentAfterChild { number: 0, parent: 0 };
+    assert_eq!(ByteTrie16::from_edges_auto(&[(Some(0), 1, true, false)]), Err(err));
+    let err = BuildError::ParentAfterChild { number: 1, parent: 2 };
+    let forward = [(None, 1, false, false), (Some(2), 2, true, false), (Some(0), 3, true, false)];
+    assert_eq!(ByteTrie16::from_edges_auto(&forward), Err(err));
+    let wide: Vec<_> = (0..17).map(|i| (None, i, true, false)).collect();
+    assert_eq!(ByteTrie16::from_edges_auto(&wide), Err(BuildError::TooManyNodes(17)));
+}
+
+#[test]
+fn test_from_iter() {
+    let edge = |parent, number| Edge { parent, label: number as u8, number, has_value: true, has_branch: false };
+    let edges = vec![edge(None, 0), edge(Some(0), 1), edge(Some(0), 2)];
+    let collected: ByteTrie16 = edges.iter().cloned().collect();
+    assert_eq!(collected, ByteTrie16::new(&edges.iter().cloned().collect()));
+    assert_eq!(collected.into_iter().collect::<Vec<_>>(), edges);
+    assert_eq!(collected.into_iter().collect::<ByteTrie16>().to_bytes(), collected.to_bytes());
+    // Duplicates collapse into one edge.
+    let collected: ByteTrie16 = edges.iter().chain(&edges).cloned().collect();
+    assert_eq!(collected, ByteTrie16::new(&edges.iter().cloned().collect()));
+
+    let wide = std::panic::catch_unwind(|| (0..17).map(|i| edge(None, i)).collect::<ByteTrie16>());
+    assert!(wide.is_err());
+}
+
+#[test]
+fn test_hash() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec()];
+    let mut set = HashSet::new();
+    assert!(set.insert(ByteTrie16::from_keys(keys.clone()).unwrap()));
+    assert!(!set.insert(ByteTrie16::from_keys(keys).unwrap()));
+    assert!(set.insert(ByteTrie16::from_keys(vec![b"dog".to_vec()]).unwrap()));
+    assert!(set.insert(ByteTrie16::empty()));
+    assert_eq!(set.len(), 3);
+}
+
+#[test]
+fn test_wide() {
+    let edge = |parent, label, number, has_value| WideEdge { parent, label, number, has_value, has_branch: false };
+    // Roots that only differ in one byte of their label, and a chain deep enough for `traverse_deep`.
+    let mut edges = vec![
+        edge(None, 0x0041, 0, false),
+        edge(None, 0x0141, 1, true),
+        edge(None, 0x4100, 2, true),
+        edge(Some(0), 0x00e9, 3, true),
+    ];
+    for number in 4..14 {
+        edges.push(edge(Some(number - 1), 0x3000 + number as u16, number, number == 13));
+    }
+    let t = WideTrie16::new(&edges.iter().cloned().collect());
+
+    assert_eq!(t.traverse_slice(&[0x0041]), Lookup::Exists);
+    assert_eq!(t.traverse_slice(&[0x0141]), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(&[0x4100]), Lookup::Value(1));
+    assert_eq!(t.traverse_slice(&[0x4141]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&[0x0041, 0x00e9]), Lookup::Value(2));
+    assert_eq!(t.traverse_slice(&[0x0041, 0x01e9]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&[0x0141, 0x00e9]), Lookup::NoSuchPath);
+    let deep: Vec<u16> = [0x0041, 0x00e9].iter().cloned().chain((4..14).map(|n| 0x3000 + n)).collect();
+    assert_eq!(t.traverse_slice(&deep), Lookup::Value(3));
+    assert_eq!(t.traverse_slice(&deep[..11]), Lookup::Exists);
+    assert_eq!(t.traverse_slice(&[]), Lookup::NoSuchPath);
+
+    // The same edges past lane 32 of a 64 lane trie, where the parent pointers need the sixth bit.
+    let shifted = |e: &WideEdge| WideEdge { parent: e.parent.map(|p| p + 40), number: e.number + 40, ..*e };
+    let t = WideTrie64::new(&edges.iter().map(shifted).collect());
+    assert_eq!(t.traverse_slice(&[0x0141]), Lookup::Value(0));
+    assert_eq!(t.traverse_slice(&[0x0041, 0x00e9]), Lookup::Value(2));
+    assert_eq!(t.traverse_slice(&[0x0141, 0x00e9]), Lookup::NoSuchPath);
+    assert_eq!(t.traverse_slice(&deep), Lookup::Value(3));
+    assert_eq!(t.traverse_slice(&deep[..11]), Lookup::Exists);
+
+    let try_new = |edges: &[WideEdge]| WideTrie16::try_new(&edges.iter().cloned().collect()).err();
+    let err = BuildError::DuplicateWideSiblingLabel { parent: None, label: 0x0141 };
+    assert_eq!(try_new(&[edge(None, 0x0141, 0, true), edge(None, 0x0141, 1, true)]), Some(err));
+    assert_eq!(try_new(&[edge(None, 1, 0, true), edge(None, 2, 0, true)]), Some(BuildError::DuplicateNumber(0)));
+    assert_eq!(try_new(&[edge(None, 1, 16, true)]), Some(BuildError::TooManyNodes(17)));
+    let err = BuildError::OrphanEdge { number: 1, parent: 5 };
+    assert_eq!(try_new(&[edge(None, 1, 0, false), edge(Some(5), 1, 1, true)]), Some(err));
+}
+
+#[test]
+fn test_remapped() {
+    let keys: Vec<Vec<u16>> = vec![vec![0x3b1, 0x3b2], vec![0x3b1, 0x3b3], vec![0xf600]];
+    let t = RemappedTrie16::from_keys(keys).unwrap();
+    assert_eq!(t.symbols().len(), 4);
+    assert_eq!(t.traverse(&[0x3b1, 0x3b2, 0, 0, 0, 0, 0, 0], 2), Lookup::Value(0));
+    assert_eq!(t.traverse(&[0x3b1, 0x3b3, 0, 0, 0, 0, 0, 0], 2), Lookup::Value(1));
+    assert_eq!(t.traverse(&[0xf600, 0, 0, 0, 0, 0, 0, 0], 1), Lookup::Value(2));
+    assert_eq!(t.traverse(&[0x3b1, 0, 0, 0, 0, 0, 0, 0], 1), Lookup::Exists);
+    // Unmapped symbols miss, even where their low byte would match.
+    assert_eq!(t.traverse(&[0x3b1, 0x4b2, 0, 0, 0, 0, 0, 0], 2), Lookup::NoSuchPath);
+    assert_eq!(t.traverse(&[0x3b1, 0x3b2, 0, 0, 0, 0, 0, 0], 9), Lookup::NoSuchPath);
+
+    let too_many = (0..17u16).map(|s| vec![s * 1000]);
+    assert_eq!(RemappedTrie16::from_keys(too_many), Err(BuildError::TooManyNodes(17)));
+}
+
+#[test]
+fn test_contains() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let q = |s: &[u8]| {
+        let mut query = [0u8; 8];
+        query[..s.len()].copy_from_slice(s);
+        (query, s.len())
+    };
+    for &(key, is_key, is_prefix) in &[
+        (&b"cat"[..], true, true),
+        (b"dog", true, true),
+        (b"ca", false, true),
+        (b"d", false, true),
+        (b"", false, true),
+        (b"cab", false, false),
+        (b"cats", false, false),
+        (b"x", false, false),
+    ] {
+        let (query, len) = q(key);
+        assert_eq!(t.contains_key(&query, len), is_key, "{:?}", key);
+        assert_eq!(t.contains_prefix(&query, len), is_prefix, "{:?}", key);
+    }
+    assert!(!t.contains_prefix(&[0; 8], 9));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "isn't zero past length 2")]
+fn test_traverse_padding() {
+    let t = ByteTrie16::from_keys(vec![b"ab".to_vec()]).unwrap();
+    t.traverse(b"abc\0\0\0\0\0", 2);
+}
+
+// Every method taking a padded query checks it, not just `traverse`.
+#[test]
+#[cfg(debug_assertions)]
+fn test_prefix_padding() {
+    let t = ByteTrie16::from_keys(vec![b"ab".to_vec()]).unwrap();
+    let query = b"abc\0\0\0\0\0";
+    let checks: Vec<Box<dyn Fn()>> = vec![
+        Box::new(|| { let _ = t.longest_prefix_match(query, 2); }),
+        Box::new(|| { let _ = t.prefix_len(query, 2); }),
+        Box::new(|| { let _ = t.explain(query, 2); }),
+        Box::new(|| { let _ = t.contains_prefix(query, 2); }),
+        Box::new(|| { let _ = t.traverse_fuzzy1(query, 2); }),
+        Box::new(|| { let _ = t.values_with_prefix(query, 2); }),
+        Box::new(|| { let _ = t.branches_under_prefix(query, 2); }),
+        Box::new(|| { let _ = t.next_bytes(query, 2); }),
+    ];
+    for check in checks {
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(check)).is_err());
+    }
+}
+
+#[test]
+fn test_traverse_ci() {
+    let keys = vec![b"accept".to_vec(), b"host".to_vec(), b"x-id".to_vec(), b"[".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let ci = |s: &[u8]| {
+        let mut query = [0u8; 8];
+        query[..s.len()].copy_from_slice(s);
+        t.traverse_ci(&query, s.len())
+    };
+    // '[' is '{' without 0x20, but it isn't a letter so it isn't folded.
+    assert_eq!(ci(b"["), Lookup::Value(0));
+    assert_eq!(ci(b"Accept"), Lookup::Value(1));
+    assert_eq!(ci(b"HOST"), Lookup::Value(2));
+    assert_eq!(ci(b"hOsT"), Lookup::Value(2));
+    assert_eq!(ci(b"X-Id"), Lookup::Value(3));
+    assert_eq!(ci(b"hosts"), Lookup::NoSuchPath);
+    assert_eq!(ci(b"{"), Lookup::NoSuchPath);
+}
+
+#[test]
+fn test_reverse_query() {
+    assert_eq!(reverse_query(b"abc"), (*b"cba\0\0\0\0\0", 3));
+    assert_eq!(reverse_query(b""), ([0; 8], 0));
+    assert_eq!(reverse_query(b"abcdefgh"), (*b"hgfedcba", 8));
+    assert_eq!(reverse_query(b"abcdefghi").1, 9);
+
+    let suffixes = vec![b"gro.".to_vec(), b"moc.".to_vec(), b"ku.oc.".to_vec()];
+    let t = ByteTrie16::from_keys(suffixes).unwrap();
+    let (query, query_len) = reverse_query(b".com");
+    assert_eq!(t.traverse(&query, query_len), Lookup::Value(2));
+    let (query, query_len) = reverse_query(b".co.uk");
+    assert_eq!(t.traverse(&query, query_len), Lookup::Value(1));
+    let (query, query_len) = reverse_query(b"com");
+    assert_eq!(t.traverse(&query, query_len), Lookup::Exists);
+}
+
+#[test]
+fn test_cursor() {
+    let t = ByteTrie16::from_keys(vec![b"ca".to_vec(), b"cat".to_vec(), b"dog".to_vec()]).unwrap();
+    let mut cursor = t.cursor();
+    assert_eq!(cursor.step(b'c'), CursorState::Partial);
+    let mut fork = cursor;
+    assert_eq!(cursor.step(b'a'), CursorState::Match(Lookup::Value(0)));
+    assert_eq!(cursor.step(b't'), CursorState::Match(Lookup::Value(1)));
+    assert_eq!(cursor.step(b's'), CursorState::Dead);
+    // Once dead, always dead, even on bytes that would match from the root.
+    assert_eq!(cursor.step(b'c'), CursorState::Dead);
+    assert_eq!(fork.step(b'o'), CursorState::Dead);
+
+    let mut cursor = t.cursor();
+    assert_eq!(cursor.step(b'a'), CursorState::Dead);
+    assert_eq!(ByteTrie16::empty().cursor().step(0), CursorState::Dead);
+}
+
+#[test]
+fn test_traverse_wildcard() {
+    let keys = vec![b"abc".to_vec(), b"abd".to_vec(), b"axc".to_vec(), b"bbc".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let pattern = |p: &[u8]| {
+        let mut pattern = [None; 8];
+        for (slot, &byte) in pattern.iter_mut().zip(p) {
+            *slot = if byte == b'?' { None } else { Some(byte) };
+        }
+        pattern
+    };
+    assert_eq!(t.traverse_wildcard(&pattern(b"a?c"), 3), vec![Lookup::Value(0), Lookup::Value(2)]);
+    assert_eq!(t.traverse_wildcard(&pattern(b"?bc"), 3), vec![Lookup::Value(0), Lookup::Value(3)]);
+    assert_eq!(t.traverse_wildcard(&pattern(b"ab?"), 3), vec![Lookup::Value(0), Lookup::Value(1)]);
+    assert_eq!(t.traverse_wildcard(&pattern(b"???"), 3).len(), 4);
+    assert_eq!(t.traverse_wildcard(&pattern(b"??"), 2), vec![]);
+    assert_eq!(t.traverse_wildcard(&pattern(b"a?e"), 3), vec![]);
+    assert_eq!(t.traverse_wildcard(&pattern(b""), 0), vec![]);
+}
+
+#[test]
+fn test_traverse_fuzzy1() {
+    let keys = vec![b"cat".to_vec(), b"cot".to_vec(), b"cut".to_vec(), b"dog".to_vec(), b"ca".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let q = |s: &[u8]| {
+        let mut query = [0u8; 8];
+        query[..s.len()].copy_from_slice(s);
+        query
+    };
+    // The exact match fits every position's wildcard, but only comes back once.
+    assert_eq!(t.traverse_fuzzy1(&q(b"cat"), 3), vec![Lookup::Value(1), Lookup::Value(2), Lookup::Value(3)]);
+    assert_eq!(t.traverse_fuzzy1(&q(b"cxt"), 3), vec![Lookup::Value(1), Lookup::Value(2), Lookup::Value(3)]);
+    assert_eq!(t.traverse_fuzzy1(&q(b"dot"), 3), vec![Lookup::Value(2), Lookup::Value(4)]);
+    assert_eq!(t.traverse_fuzzy1(&q(b"xx"), 2), vec![]);
+    assert_eq!(t.traverse_fuzzy1(&q(b"xa"), 2), vec![Lookup::Value(0)]);
+    assert_eq!(t.traverse_fuzzy1(&q(b""), 0), vec![]);
+}
+
+#[test]
+fn test_traverse_batch() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"ca".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let queries: Vec<([u8; 8], usize)> = [&b"ca"[..], b"cat", b"c", b"dog", b"dot", b"", b"cart"]
+        .iter()
+        .map(|q| {
+            let mut query = [0u8; 8];
+            query[..q.len()].copy_from_slice(q);
+            (query, q.len())
+        })
+        .collect();
+    let mut out: Vec<_> = queries.iter().map(|_| Lookup::NoSuchPath).collect();
+    t.traverse_batch(&queries, &mut out);
+    for ((query, len), lookup) in queries.iter().zip(&out) {
+        assert_eq!(t.traverse(query, *len), *lookup);
+    }
+
+    let mut queries = queries;
+    queries.push(([0; 8], 9));
+    for window in queries.windows(4) {
+        let batch = [window[0].0, window[1].0, window[2].0, window[3].0];
+        let lookups = t.traverse4(&batch, &[window[0].1, window[1].1, window[2].1, window[3].1]);
+        for ((query, len), lookup) in window.iter().zip(&lookups) {
+            assert_eq!(t.traverse(query, *len), *lookup);
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_traverse() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"ca".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let queries: Vec<([u8; 8], usize)> = (0..10_000u32)
+        .map(|i| {
+            let bytes = b"cardogtx";
+            let len = (i % 5) as usize;
+            let mut query = [0u8; 8];
+            for (j, slot) in query[..len].iter_mut().enumerate() {
+                *slot = bytes[(i as usize >> (3 * j)) % 8];
+            }
+            (query, len)
+        })
+        .collect();
+    let out = t.par_traverse(&queries);
+    assert_eq!(out.len(), queries.len());
+    for ((query, len), lookup) in queries.iter().zip(&out) {
+        assert_eq!(t.traverse(query, *len), *lookup);
+    }
+}
+
+#[test]
+fn test_values_with_prefix() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"ca".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    assert_eq!(t.values_with_prefix(b"c\0\0\0\0\0\0\0", 1), vec![0, 1, 2]);
+    assert_eq!(t.values_with_prefix(b"ca\0\0\0\0\0\0", 2), vec![0, 1, 2]);
+    assert_eq!(t.values_with_prefix(b"car\0\0\0\0\0", 3), vec![1]);
+    assert_eq!(t.values_with_prefix(b"do\0\0\0\0\0\0", 2), vec![3]);
+    assert_eq!(t.values_with_prefix(b"x\0\0\0\0\0\0\0", 1), Vec::<u8>::new());
+    assert_eq!(t.values_with_prefix(&[0; 8], 0), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_branches_under_prefix() {
+    let t = ByteTrie16::new(&crate::trie! {
+        b'a' = branch => { b'b' = branch => { b'c' = value + branch }, b'd' = branch },
+        b'x' = branch,
+    });
+    assert_eq!(t.branches_under_prefix(b"a\0\0\0\0\0\0\0", 1), vec![1, 2, 3]);
+    assert_eq!(t.branches_under_prefix(b"ab\0\0\0\0\0\0", 2), vec![2]);
+    assert_eq!(t.branches_under_prefix(b"abc\0\0\0\0\0", 3), Vec::<u8>::new());
+    assert_eq!(t.branches_under_prefix(b"x\0\0\0\0\0\0\0", 1), Vec::<u8>::new());
+    assert_eq!(t.branches_under_prefix(b"q\0\0\0\0\0\0\0", 1), Vec::<u8>::new());
+    assert_eq!(t.branches_under_prefix(&[0; 8], 0), vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_next_bytes() {
+    let t = ByteTrie16::from_strs(["cat", "car", "cab", "dog", "d"]).unwrap();
+    assert_eq!(t.next_bytes(&[0; 8], 0), b"cd".to_vec());
+    assert_eq!(t.next_bytes(b"ca\0\0\0\0\0\0", 2), b"brt".to_vec());
+    assert_eq!(t.next_bytes(b"d\0\0\0\0\0\0\0", 1), b"o".to_vec());
+    assert_eq!(t.next_bytes(b"cat\0\0\0\0\0", 3), Vec::<u8>::new());
+    assert_eq!(t.next_bytes(b"x\0\0\0\0\0\0\0", 1), Vec::<u8>::new());
+    assert_eq!(ByteTrie16::empty().next_bytes(&[0; 8], 0), Vec::<u8>::new());
+}
+
+#[test]
+fn test_longest_prefix_match() {
+    let keys = vec![vec![10], vec![10, 1], vec![10, 1, 2, 3], vec![192, 168]];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    assert_eq!(t.longest_prefix_match(&[10, 1, 2, 3, 4, 0, 0, 0], 5), Some((2, 4)));
+    assert_eq!(t.longest_prefix_match(&[10, 1, 2, 0, 0, 0, 0, 0], 3), Some((1, 2)));
+    assert_eq!(t.longest_prefix_match(&[10, 2, 0, 0, 0, 0, 0, 0], 2), Some((0, 1)));
+    assert_eq!(t.longest_prefix_match(&[192, 168, 0, 1, 0, 0, 0, 0], 4), Some((3, 2)));
+    assert_eq!(t.longest_prefix_match(&[192, 0, 0, 0, 0, 0, 0, 0], 2), None);
+    assert_eq!(t.longest_prefix_match(&[0; 8], 0), None);
+
+    // Unlike `longest_prefix_match`, `prefix_len` doesn't care whether a value ends there.
+    assert_eq!(t.prefix_len(&[10, 1, 2, 0, 0, 0, 0, 0], 3), 3);
+    assert_eq!(t.prefix_len(&[192, 0, 0, 0, 0, 0, 0, 0], 2), 1);
+    assert_eq!(t.prefix_len(&[7, 0, 0, 0, 0, 0, 0, 0], 1), 0);
+    assert_eq!(t.prefix_len(&[10, 1, 2, 3, 0, 0, 0, 0], 12), 4);
+}
+
+#[test]
+fn test_keys() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"ca".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let found: Vec<_> = t.keys().collect();
+    assert_eq!(
+        found,
+        vec![
+            (b"ca".to_vec(), Lookup::Value(0)),
+            (b"car".to_vec(), Lookup::Value(1)),
+            (b"cat".to_vec(), Lookup::Value(2)),
+            (b"dog".to_vec(), Lookup::Value(3)),
+        ]
+    );
+    assert_eq!(ByteTrie16::new(&BTreeSet::new()).keys().count(), 0);
+}
+
+#[test]
+fn test_bytes() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys.clone()).unwrap();
+    let bytes = t.to_bytes();
+    assert_eq!(ByteTrie16::from_bytes(bytes), t);
+    assert_eq!(ByteTrie16::from_bytes_checked(bytes), Ok(t));
+    let (edges, nodes) = t.packed();
+    assert_eq!(ByteTrie16::from_packed(edges, nodes), t);
+    assert_eq!([&edges[..], &nodes[..]].concat(), &bytes[..]);
+    let wide = ByteTrie32::from_keys(keys.clone()).unwrap();
+    assert_eq!(ByteTrie32::from_bytes_checked(wide.to_bytes()), Ok(wide));
+
+    // 64 lane nodes go low byte first, so with nothing past lane 31 for a parent the bytes are the
+    // 32 lane trie's with a zero after each node.
+    let widest = ByteTrie64::from_keys(keys).unwrap();
+    let bytes = widest.to_bytes();
+    assert_eq!(ByteTrie64::from_bytes_checked(bytes), Ok(widest));
+    assert_eq!(&bytes[..32], &wide.to_bytes()[..32]);
+    assert!(bytes[64..].chunks(2).zip(&wide.to_bytes()[32..]).all(|(node, &byte)| node == [byte, 0]));
+    // "cat", "car", and "dog" use lanes 0 to 5, so the `a` in lane 1 pointing at lane 32 is dangling.
+    let mut dangling = bytes;
+    dangling[64 + 2 + 1] = 1;
+    assert_eq!(ByteTrie64::from_bytes_checked(dangling), Err(ValidationError::DanglingParent(1)));
+
+    // "ab", with `b` in lane 1, built at compile time.
+    const AB: ByteTrie16 = ByteTrie16::from_packed_const(
+        [b'a', b'b', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        [0b1000_0000, 0b0100_0000, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111,
+         0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111, 0b1001_1111],
+    );
+    assert_eq!(AB, ByteTrie16::from_keys(vec![b"ab".to_vec()]).unwrap());
+    assert_eq!(AB.traverse_slice(b"ab"), Lookup::Value(0));
+    assert_eq!(AB.max_depth(), 2);
+    let (edges, nodes) = wide.packed();
+    assert_eq!(ByteTrie32::from_packed_const(edges, nodes), wide);
+
+    // "ab", with `b` in lane 1 and every other lane unused.
+    let mut good = [0u8; 32];
+    good[16..].copy_from_slice(&[0b1001_1111; 16]);
+    good[0] = b'a';
+    good[16] = 0b1000_0000;
+    good[1] = b'b';
+    good[17] = 0b0100_0000;
+    let t = ByteTrie16::from_bytes_checked(good).unwrap();
+    assert_eq!(t.traverse_slice(b"ab"), Lookup::Value(0));
+
+    let check = |patch: &[(usize, u8)]| {
+        let mut bytes = good;
+        for &(i, byte) in patch {
+            bytes[i] = byte;
+        }
+        ByteTrie16::from_bytes_checked(bytes).err()
+    };
+    assert_eq!(check(&[(5, b'x')]), Some(ValidationError::UnusedLabel(5)));
+    assert_eq!(check(&[(16, 0b1000_0001)]), Some(ValidationError::RootWithParent(0)));
+    assert_eq!(check(&[(17, 2)]), Some(ValidationError::DanglingParent(1)));
+    assert_eq!(check(&[(17, 20)]), Some(ValidationError::DanglingParent(1)));
+    assert_eq!(check(&[(16, 1), (17, 0)]), Some(ValidationError::TooDeep(0)));
+    assert_eq!(check(&[(2, b'a'), (18, 0b1000_0000)]), Some(ValidationError::DuplicateSiblingLabel(2)));
+    assert_eq!(check(&[(2, b'b'), (18, 0b0000_0000)]), Some(ValidationError::DuplicateSiblingLabel(2)));
+
+    assert_eq!(t.validate(), Ok(()));
+    let (edges, mut nodes) = t.packed();
+    nodes[1] = 0b0000_0011;
+    assert_eq!(ByteTrie16::from_packed(edges, nodes).validate(), Err(ValidationError::DanglingParent(1)));
+}
+
+#[test]
+fn test_corrupt_bytes() {
+    // "ab" with `b` pointing at lane 31, past the last of 16. `from_bytes` doesn't check, and the
+    // scalar backend's shuffle has to read the missing parent as 0 instead of panicking.
+    let mut bytes = ByteTrie16::from_keys(vec![b"ab".to_vec()]).unwrap().to_bytes();
+    bytes[16 + 1] |= 31;
+    assert_eq!(ByteTrie16::from_bytes_checked(bytes), Err(ValidationError::DanglingParent(1)));
+    let t = ByteTrie16::from_bytes(bytes);
+    let (edges, nodes) = t.packed();
+    let nodes = SplitNodes::<Scalar<16>>::from_words(&nodes.map(NodeWord::widen));
+    let lanes = Lanes::new(nodes, Scalar::<16>::from_slice(&edges));
+    assert_eq!(super::traverse(&lanes, b"a\0\0\0\0\0\0\0", 1), Lookup::Exists);
+    assert_eq!(super::traverse(&lanes, b"ab\0\0\0\0\0\0", 2), Lookup::NoSuchPath);
+    let mut deep = [0u8; 16];
+    deep[..2].copy_from_slice(b"ab");
+    assert_eq!(super::traverse_deep(&lanes, &deep, 2), Lookup::NoSuchPath);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let keys = vec![b"cat".to_vec(), b"car".to_vec(), b"dog".to_vec()];
+    let t = ByteTrie16::from_keys(keys).unwrap();
+    let json = serde_json::to_string(&t).unwrap();
+    assert_eq!(serde_json::from_str::<ByteTrie16>(&json).unwrap(), t);
+
+    let mut edges = [0u8; 16];
+    let mut nodes = [0b1001_1111u8; 16];
+    edges[0] = b'a';
+    nodes[0] = 0b1000_0000;
+    edges[1] = b'b';
+    nodes[1] = 0b0100_0000;
+    let ok = serde_json::json!({ "edges": edges, "nodes": nodes });
+    let u: ByteTrie16 = serde_json::from_value(ok).unwrap();
+    assert_eq!(u.traverse_slice(b"ab"), Lookup::Value(0));
+
+    let bad = |edges: &[u8], nodes: &[u8]| {
+        let json = serde_json::json!({ "edges": edges, "nodes": nodes });
+        serde_json::from_value::<ByteTrie16>(json).is_err()
+    };
+    // Wrong lane count.
+    assert!(bad(&edges[..8], &nodes[..8]));
+    // Cycle that never reaches a root; `test_bytes` covers the rest of the checks.
+    let mut n = nodes;
+    n[0] = 1;
+    n[1] = 0;
+    assert!(bad(&edges, &n));
+
+    // 64 lane tries have two bytes per node, and a narrower trie won't read one.
+    let wide = ByteTrie64::from_keys(vec![b"cat".to_vec(), b"dog".to_vec()]).unwrap();
+    let json = serde_json::to_string(&wide).unwrap();
+    assert_eq!(serde_json::from_str::<ByteTrie64>(&json).unwrap(), wide);
+    let narrow = serde_json::to_value(ByteTrie64::from_keys(vec![b"ab".to_vec()]).unwrap()).unwrap();
+    assert!(serde_json::from_value::<ByteTrie16>(narrow).is_err());
 }
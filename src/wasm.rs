@@ -0,0 +1,110 @@
+use crate::simd::{Mask, Vector};
+use core::arch::wasm32::*;
+use core::ops::{BitAnd, BitOr, BitOrAssign, Shl, Shr};
+
+// 16 lanes in a single `v128`. The packed_simd calls `traverse` was written against map onto
+// SIMD128 directly:
+//
+// * `shuffle1_dyn` -> `i8x16_swizzle`, which zeroes lanes whose index is 16 or more instead of
+//   wrapping like `pshufb`. `Lanes::new` only produces in-range indices, so they agree.
+// * `eq`/`ne` -> `u8x16_eq`/`u8x16_ne`, which produce all-ones lanes, and `select` ->
+//   `v128_bitselect` over those.
+// * `bitmask` -> `u8x16_bitmask`, which already returns one bit per lane.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct Wasm128(v128);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Wasm128Mask(v128);
+
+impl PartialEq for Wasm128 {
+    fn eq(&self, other: &Self) -> bool {
+        u8x16_all_true(u8x16_eq(self.0, other.0))
+    }
+}
+
+impl Eq for Wasm128 {}
+
+impl BitAnd for Wasm128 {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Wasm128(v128_and(self.0, other.0))
+    }
+}
+
+impl BitOr for Wasm128 {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Wasm128(v128_or(self.0, other.0))
+    }
+}
+
+impl BitOrAssign for Wasm128 {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = *self | other;
+    }
+}
+
+impl Shl<u32> for Wasm128 {
+    type Output = Self;
+
+    fn shl(self, amount: u32) -> Self {
+        Wasm128(u8x16_shl(self.0, amount))
+    }
+}
+
+impl Shr<u32> for Wasm128 {
+    type Output = Self;
+
+    fn shr(self, amount: u32) -> Self {
+        Wasm128(u8x16_shr(self.0, amount))
+    }
+}
+
+impl Vector for Wasm128 {
+    type Mask = Wasm128Mask;
+    type Bitmask = u16;
+
+    fn splat(byte: u8) -> Self {
+        Wasm128(u8x16_splat(byte))
+    }
+
+    fn from_slice(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..16];
+        // SAFETY: `bytes` is 16 bytes long, and `v128_load` doesn't need them aligned.
+        Wasm128(unsafe { v128_load(bytes.as_ptr() as *const v128) })
+    }
+
+    fn write_to_slice(self, bytes: &mut [u8]) {
+        let bytes = &mut bytes[..16];
+        // SAFETY: `bytes` is 16 bytes long, and `v128_store` doesn't need them aligned.
+        unsafe { v128_store(bytes.as_mut_ptr() as *mut v128, self.0) }
+    }
+
+    fn simd_eq(self, other: Self) -> Wasm128Mask {
+        Wasm128Mask(u8x16_eq(self.0, other.0))
+    }
+
+    fn simd_ne(self, other: Self) -> Wasm128Mask {
+        Wasm128Mask(u8x16_ne(self.0, other.0))
+    }
+
+    fn shuffle(self, indices: Self) -> Self {
+        Wasm128(i8x16_swizzle(self.0, indices.0))
+    }
+}
+
+impl Mask for Wasm128Mask {
+    type Vector = Wasm128;
+    type Bitmask = u16;
+
+    fn select(self, a: Wasm128, b: Wasm128) -> Wasm128 {
+        Wasm128(v128_bitselect(a.0, b.0, self.0))
+    }
+
+    fn bitmask(self) -> u16 {
+        u8x16_bitmask(self.0)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::simd::{NodeLanes, NodeOf, NodeWord, NodesOf, Vector, VectorOf};
+use crate::{
+    build_tree, classify, follow_deep, level_mask, match_bitsets, match_levels, BuildError, Edge, Lanes, LaneCount,
+    Lookup, SupportedLaneCount, MAX_DEPTH,
+};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+// A trie over 16 bit labels, e.g. UTF-16 code units. Each label is split across two lanes of
+// bytes, and a lane only matches a query position when both halves do. The nodes are the same as
+// a `ByteTrie`'s, so everything past the edge matches is shared with it.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct WideTrie<const LANES: usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    nodes: NodesOf<LANES>,
+    // High and low bytes of each incoming edge's label.
+    high: VectorOf<LANES>,
+    low: VectorOf<LANES>,
+}
+
+pub type WideTrie16 = WideTrie<16>;
+pub type WideTrie32 = WideTrie<32>;
+pub type WideTrie64 = WideTrie<64>;
+
+// Same as `Edge`, but with a 16 bit label.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct WideEdge {
+    pub parent: Option<usize>,
+    pub label: u16,
+    pub number: usize,
+    pub has_value: bool,
+    pub has_branch: bool,
+}
+
+impl<const LANES: usize> WideTrie<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    // Panics if `try_new` would return an error.
+    pub fn new(edges: &BTreeSet<WideEdge>) -> Self {
+        Self::try_new(edges).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(edges: &BTreeSet<WideEdge>) -> Result<Self, BuildError> {
+        if edges.len() > LANES {
+            return Err(BuildError::TooManyNodes(edges.len()));
+        }
+
+        // `build_tree` only knows about byte labels, so check the wide ones here and hand it a
+        // label per edge that can't collide with a sibling's: with every number in range, the
+        // number itself. The real labels go in afterwards.
+        let mut edge_numbers = BTreeSet::new();
+        let mut sibling_labels = BTreeSet::new();
+        for edge in edges {
+            if !edge_numbers.insert(edge.number) {
+                return Err(BuildError::DuplicateNumber(edge.number));
+            }
+            if !sibling_labels.insert((edge.parent, edge.label)) {
+                return Err(BuildError::DuplicateWideSiblingLabel { parent: edge.parent, label: edge.label });
+            }
+            if edge.number >= LANES {
+                return Err(BuildError::TooManyNodes(edge.number + 1));
+            }
+        }
+        let mut narrow: Vec<Edge> = edges
+            .iter()
+            .map(|e| Edge {
+                parent: e.parent,
+                label: e.number as u8,
+                number: e.number,
+                has_value: e.has_value,
+                has_branch: e.has_branch,
+            })
+            .collect();
+        narrow.sort();
+        let (_, packed_nodes) = build_tree::<LANES, NodeOf<LANES>>(&narrow, MAX_DEPTH)?;
+
+        let mut high = [0u8; LANES];
+        let mut low = [0u8; LANES];
+        for edge in edges {
+            let [hi, lo] = edge.label.to_be_bytes();
+            high[edge.number] = hi;
+            low[edge.number] = lo;
+        }
+        Ok(Self {
+            nodes: NodesOf::<LANES>::from_words(&packed_nodes.map(NodeWord::widen)),
+            high: VectorOf::<LANES>::from_slice(&high),
+            low: VectorOf::<LANES>::from_slice(&low),
+        })
+    }
+
+    // Same as `ByteTrie::traverse`, over 16 bit labels.
+    pub fn traverse(&self, query: &[u16; 8], query_len: usize) -> Lookup {
+        if query_len == 0 || query_len > 8 {
+            return Lookup::NoSuchPath;
+        }
+        let lanes = self.lanes();
+        let levels = match_levels(&lanes, self.match_bitsets(query));
+        classify(lanes.nodes, level_mask(levels[query_len - 1], query_len))
+    }
+
+    // Same as `ByteTrie::traverse_deep`, over 16 bit labels.
+    pub fn traverse_deep(&self, query: &[u16; 16], query_len: usize) -> Lookup {
+        if query_len == 0 || query_len > 16 {
+            return Lookup::NoSuchPath;
+        }
+        let mut lo_query = [0u16; 8];
+        let mut hi_query = [0u16; 8];
+        lo_query.copy_from_slice(&query[..8]);
+        hi_query.copy_from_slice(&query[8..]);
+        follow_deep(&self.lanes(), self.match_bitsets(&lo_query), self.match_bitsets(&hi_query), query_len)
+    }
+
+    // Same as `ByteTrie::traverse_slice`, over 16 bit labels.
+    pub fn traverse_slice(&self, query: &[u16]) -> Lookup {
+        if query.len() <= 8 {
+            let mut padded = [0u16; 8];
+            padded[..query.len()].copy_from_slice(query);
+            return self.traverse(&padded, query.len());
+        }
+        if query.len() <= 16 {
+            let mut padded = [0u16; 16];
+            padded[..query.len()].copy_from_slice(query);
+            return self.traverse_deep(&padded, query.len());
+        }
+        Lookup::NoSuchPath
+    }
+
+    // `Lanes` only looks at its edges to match queries, which happens here instead, so it gets
+    // the low bytes just to fill the slot.
+    fn lanes(&self) -> Lanes<VectorOf<LANES>> {
+        Lanes::new(self.nodes, self.low)
+    }
+
+    // `match_bitsets` for each half of the labels, so a bit survives when both halves match.
+    fn match_bitsets(&self, query: &[u16; 8]) -> VectorOf<LANES> {
+        let mut hi_query = [0u8; 8];
+        let mut lo_query = [0u8; 8];
+        for (i, label) in query.iter().enumerate() {
+            let [hi, lo] = label.to_be_bytes();
+            hi_query[i] = hi;
+            lo_query[i] = lo;
+        }
+        match_bitsets(self.high, &hi_query) & match_bitsets(self.low, &lo_query)
+    }
+}